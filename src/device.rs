@@ -0,0 +1,49 @@
+use byte::BitNumber;
+use memory;
+use util;
+use world::World;
+
+// Devices act on RAM, so unlike other items they need the whole world.
+// They aren't used up, and activating one always reveals what it is.
+
+// flip 10 random bits in memory
+pub fn corrupt(world: &mut World) {
+    world.log.tell("It shrieks, and your memory crackles with static!");
+    memory::flip_random_bits(world, 10);
+}
+
+// set the low nibble of the selected address
+pub fn offset(world: &mut World, nibble: u8) {
+    let address = world.player.selected;
+    let value = memory::peek(world, address);
+    let result = value & 0xf0 | nibble & 0x0f;
+    world.log.tell(format!("It clicks into the {}. ({:02x} -> {:02x})",
+        util::address_name(address), value, result));
+    memory::poke(world, address, result);
+}
+
+// copy the selected address into the accumulator
+pub fn copy(world: &mut World) {
+    let address = world.player.selected;
+    world.player.accumulator = memory::peek(world, address);
+    world.log.tell(format!("It hums as it reads {:02x} from the {}.",
+        world.player.accumulator, util::address_name(address)));
+}
+
+// write the accumulator back to the selected address
+pub fn paste(world: &mut World) {
+    let address = world.player.selected;
+    let value = world.player.accumulator;
+    world.log.tell(format!("It hums as it writes {:02x} to the {}.",
+        value, util::address_name(address)));
+    memory::poke(world, address, value);
+}
+
+// announce and identify the device in the given inventory slot
+pub fn activate(world: &mut World, index: BitNumber) {
+    if let Some(item) = world.player.inventory.slots[index as usize].get_item() {
+        world.log.tell(format!("You activate the {}.",
+            item.name(world.player.identification, &world.item_appearance_map)));
+        world.player.identify(&mut world.log, &world.item_appearance_map, item);
+    }
+}
@@ -20,7 +20,7 @@ use sprite::Sprite;
 use tile::{Tile, Stairs};
 use util::{coin_flip, random_range, random_range_two, sample};
 use world::World;
-use monster::Monster;
+use monster::{self, Monster};
 use fov::FOV_RADIUS;
 
 
@@ -114,6 +114,35 @@ fn spawn_monsters(depth: u8, map: &Grid<Tile>) -> [Monster; 5] {
 }
 
 
+// The golden pendant waits on this level, guarded by the golden dragon.
+pub const PENDANT_DEPTH: u8 = 20;
+
+// put the pendant on the floor tile furthest from the upstairs,
+// with the golden dragon on the closest free tile next to it
+fn place_pendant(level: &mut Level) {
+    let upstairs = grid::RECTANGLE.into_iter().find(|p|
+        level.tiles[*p] == Tile::Stairs(Stairs::Up)).unwrap();
+    let pendant_position = grid::RECTANGLE.into_iter()
+        .filter(|p| level.tiles[*p] == Tile::Floor)
+        .max_by_key(|p| (p.cheby_dist(upstairs), p.taxi_dist(upstairs)))
+        .unwrap();
+    level.items.insert(pendant_position, Item::golden_pendant());
+
+    let guard_position = grid::RECTANGLE.into_iter()
+        .filter(|p| *p != pendant_position && *p != upstairs
+            && level.tiles[*p].is_open() && level.monster_at(*p).is_none())
+        .min_by_key(|p| (p.cheby_dist(pendant_position), p.taxi_dist(pendant_position)))
+        .unwrap();
+
+    // take a free monster slot if there is one; on a full level the dragon
+    // deliberately replaces whichever monster is closest to the pendant
+    let slot = level.monsters.iter().position(|m| !m.alive()).unwrap_or_else(||
+        (0..level.monsters.len())
+            .min_by_key(|&i| level.monsters[i].position.cheby_dist(pendant_position))
+            .unwrap());
+    level.monsters[slot] = Monster::new(monster::Kind::GoldenDragon, guard_position);
+}
+
 // TODO: make early levels easy, handle special case for level 255
 pub fn build() -> Result<Dungeon, MapError> {
     let mut schemes = read_maps()?;
@@ -135,7 +164,7 @@ pub fn build() -> Result<Dungeon, MapError> {
 
     rand::thread_rng().shuffle(&mut maps[..]);
 
-    let dungeon = (1..).zip(maps).map(|(depth, map)| {
+    let mut dungeon: Dungeon = (1..).zip(maps).map(|(depth, map)| {
         Level {
             items: spawn_items(&map),
             monsters: spawn_monsters(depth as u8, &map),
@@ -144,6 +173,8 @@ pub fn build() -> Result<Dungeon, MapError> {
         }
     }).collect();
 
+    place_pendant(&mut dungeon[PENDANT_DEPTH as usize - 1]);
+
     Ok(dungeon)
 }
 
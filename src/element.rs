@@ -0,0 +1,8 @@
+// The four elements. Index Player::aptitude with this.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Element {
+    Metal = 0x00,
+    Acid = 0x01,
+    Fire = 0x02,
+    Elec = 0x03,
+}
@@ -3,14 +3,15 @@ use geometry::*;
 use dungeon::Level;
 use grid;
 
+// The default light radius; see memory::LIGHT_RADIUS.
 pub const FOV_RADIUS: i32 = 4;
 
-pub fn calculate(level: &Level, start: Point) -> HashSet<Point> {
+pub fn calculate(level: &Level, start: Point, radius: i32) -> HashSet<Point> {
     let mut fov: HashSet<Point> = HashSet::new();
 
     fov.insert(start);
 
-    for r in 1 .. FOV_RADIUS + 1 {
+    for r in 1 .. radius + 1 {
         for point in Rectangle::point(start).grow(r).edges() {
             // check if `point` is visible by testing each possible preceeding
             // point that line of sight could come from. these predecessors are
@@ -34,6 +34,24 @@ impl Point {
     pub fn step_towards(self, other: Point) -> Point {
         self + (other - self).direction()
     }
+
+    // The points on a Bresenham line from here to `other`, excluding this one.
+    pub fn line_to(self, other: Point) -> Vec<Point> {
+        let (Point(x0, y0), Point(x1, y1)) = (self, other);
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+
+        let mut error = dx + dy;
+        let (mut x, mut y) = (x0, y0);
+        let mut points = vec![];
+        while (x, y) != (x1, y1) {
+            let e2 = 2 * error;
+            if e2 >= dy { error += dy; x += sx; }
+            if e2 <= dx { error += dx; y += sy; }
+            points.push(Point(x, y));
+        }
+        points
+    }
 }
 
 impl Add for Point {
@@ -1,9 +1,10 @@
 use rand;
 use rand::Rng;
 use sprite::*;
+use element::Element;
 use util::{coin_flip, random_range};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq)]
 pub enum Appearance {
     Crowbar = 0x01,
     VolcanicShard = 0x02,
@@ -142,8 +143,17 @@ impl Appearance {
     pub fn sprite(self) -> Sprite {
         APPEARANCE_INFOS[self as usize - 1].sprite
     }
+
+    // This appearance's bit in the player's identification bitmask.
+    pub fn identification_mask(self) -> u32 {
+        1 << self as u32
+    }
 }
 
+// Appearances whose kind is shuffled each game: fruits, pills, and devices.
+// Everything else is identified from the start.
+pub const SHUFFLED_APPEARANCES: u32 = 0x3800ffe0;
+
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub enum Kind {
     Crowbar,
@@ -179,6 +189,46 @@ pub enum Kind {
     GoldenPendant,
 }
 
+impl Kind {
+    // Identified item names.
+    pub fn name(self) -> &'static str {
+        use self::Kind::*;
+        match self {
+            Crowbar => "crowbar",
+            VolcanicShard => "volcanic shard",
+            Taser => "taser",
+            JellyGun => "jelly gun",
+            FullHPFruit => "fruit of healing",
+            FullTPFruit => "fruit of focus",
+            CancellationFruit => "fruit of cancellation",
+            ChargePill => "pill of charge",
+            XPUpPill => "pill of experience",
+            HastePill => "pill of haste",
+            IdentifyPill => "pill of identify",
+            XPDownPill => "pill of drain",
+            PoisonPill => "pill of poison",
+            ProtectPill => "pill of protection",
+            TormentPill => "pill of torment",
+            ThickSweater => "thick sweater",
+            BallisticVest => "ballistic vest",
+            DragonScaleMail => "dragon scale mail",
+            TitaniumNecklace => "titanium necklace",
+            RustyNecklace => "rusty necklace",
+            CrimsonNecklace => "crimson necklace",
+            GlowingNecklace => "glowing necklace",
+            UnholyNecklace => "unholy necklace",
+            WandOfDeath => "wand of death",
+            Manual => "manual",
+            Guidebook => "guidebook",
+            Corruptor => "corruptor",
+            Offsetter => "offsetter",
+            Copier => "copier",
+            Palantir => "palantir",
+            GoldenPendant => "golden pendant",
+        }
+    }
+}
+
 // A map from Appearances (0x00 through 0x1f) to Kinds.
 // 0x00 is mapped to None; items are mapped to Some<Kind>.
 pub type AppearanceMap = [Option<Kind>; 0x20];
@@ -231,21 +281,31 @@ pub fn random_appearance_map() -> AppearanceMap {
         Some(GlowingNecklace),
         Some(UnholyNecklace),
         Some(WandOfDeath),
+        Some(Manual),
+        Some(Guidebook),
         Some(devices[0]),
         Some(devices[1]),
         Some(devices[2]),
-        Some(Offsetter),
-        Some(Copier),
         Some(Palantir),
         Some(GoldenPendant),
     ]
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum EquipmentSlot {
     Weapon, Body, Neck
 }
 
+impl EquipmentSlot {
+    // verbs for equipping and unequipping
+    pub fn verbs(self) -> (&'static str, &'static str) {
+        match self {
+            EquipmentSlot::Weapon => ("wield", "put away"),
+            EquipmentSlot::Body | EquipmentSlot::Neck => ("put on", "take off"),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct Item {
     // 5 high bits
@@ -258,20 +318,52 @@ pub struct Item {
 }
 
 impl Item {
+    // a random item; the golden pendant is placed separately by the dungeon
     pub fn spawn() -> Item {
         Item {
-            appearance: Appearance::from_byte(random_range(0x01..0x20)),
+            appearance: Appearance::from_byte(random_range(0x01..0x1f)),
             enchanted: coin_flip(),
             cursed: coin_flip(),
         }
     }
 
+    pub fn golden_pendant() -> Item {
+        Item {
+            appearance: Appearance::GoldenPendant,
+            enchanted: false,
+            cursed: false,
+        }
+    }
+
     pub fn sprite(&self) -> Sprite {
         self.appearance.sprite()
     }
 
-    pub fn name(&self) -> &'static str {
-        self.appearance.name()
+    pub fn appearance(&self) -> Appearance {
+        self.appearance
+    }
+
+    pub fn is_enchanted(&self) -> bool {
+        self.enchanted
+    }
+
+    pub fn is_cursed(&self) -> bool {
+        self.cursed
+    }
+
+    pub fn kind(&self, appearance_map: &AppearanceMap) -> Kind {
+        appearance_map[self.appearance as usize]
+            .expect("Item::kind - unmapped appearance")
+    }
+
+    // The true name if this item's appearance is in the identification
+    // bitmask, otherwise the unidentified name.
+    pub fn name(&self, identification: u32, appearance_map: &AppearanceMap) -> &'static str {
+        if identification & self.appearance.identification_mask() != 0 {
+            self.kind(appearance_map).name()
+        } else {
+            self.appearance.name()
+        }
     }
 
     pub fn is_consumable(&self) -> bool {
@@ -284,6 +376,53 @@ impl Item {
         }
     }
 
+    // Some((base, element)) for melee weapons, which deal XLd(base + aptitude)
+    // damage; None for everything else.
+    pub fn melee_damage(&self) -> Option<(i32, Element)> {
+        use self::Appearance::*;
+        match self.appearance {
+            Crowbar => Some((3, Element::Metal)),
+            VolcanicShard => Some((5, Element::Fire)),
+            Taser => Some((6, Element::Elec)),
+            _ => None
+        }
+    }
+
+    // Some((base, element)) for guns, which shoot for XLd(base + aptitude)
+    // damage; None for everything else.
+    pub fn ranged_damage(&self) -> Option<(i32, Element)> {
+        match self.appearance {
+            Appearance::JellyGun => Some((3, Element::Acid)),
+            _ => None
+        }
+    }
+
+    // (defense, aptitudes) added while this item is equipped.
+    // Index the aptitudes with element::Element.
+    pub fn modifiers(&self) -> (i8, [i8; 4]) {
+        use self::Appearance::*;
+        match self.appearance {
+            ThickSweater     => (3, [ 0,  0,  2, -2]),
+            BallisticVest    => (5, [ 2, -2,  0,  0]),
+            DragonScaleMail  => (7, [ 0,  2, -2,  0]),
+            TitaniumNecklace => (0, [ 3, -1, -1, -1]),
+            RustyNecklace    => (0, [-1,  3, -1, -1]),
+            CrimsonNecklace  => (0, [-1, -1,  3, -1]),
+            GlowingNecklace  => (0, [-1, -1, -1,  3]),
+            UnholyNecklace   => (0, [-3, -3, -3, -3]),
+            _                => (0, [ 0,  0,  0,  0]),
+        }
+    }
+
+    // whether attractors can pull this item
+    pub fn is_metal(&self) -> bool {
+        use self::Appearance::*;
+        match self.appearance {
+            Crowbar | BallisticVest | TitaniumNecklace => true,
+            _ => false
+        }
+    }
+
     // Some(slot) if this item is equipment; None if it isn't.
     pub fn equipment_slot(&self) -> Option<EquipmentSlot> {
         use self::Appearance::*;
@@ -349,6 +488,30 @@ impl InventorySlot {
         self.byte & EQUIP_MASK != 0
     }
 
+    // e.g. "cursed enchanted crowbar (equipped)"
+    pub fn description(self, identification: u32, appearance_map: &AppearanceMap) -> String {
+        let mut description = String::new();
+
+        if self.is_cursed() {
+            description.push_str("cursed ");
+        }
+
+        if self.is_enchanted() {
+            description.push_str("enchanted ");
+        }
+
+        description.push_str(match self.get_item() {
+            Some(item) => item.name(identification, appearance_map),
+            None => "nothing"
+        });
+
+        if self.is_equipped() {
+            description.push_str(" (equipped)");
+        }
+
+        description
+    }
+
     pub fn equip(self) -> InventorySlot {
         InventorySlot { byte: self.byte | EQUIP_MASK }
     }
@@ -368,6 +531,23 @@ impl Inventory {
         Inventory { slots: [InventorySlot::empty(); 8] }
     }
 
+    pub fn contains(&self, appearance: Appearance) -> bool {
+        self.slots.iter().filter_map(|slot| slot.get_item())
+            .any(|item| item.appearance == appearance)
+    }
+
+    // the index of the item equipped in the given equipment slot, if any
+    pub fn equipped_index(&self, equipment_slot: EquipmentSlot) -> Option<usize> {
+        self.slots.iter().position(|slot| slot.is_equipped()
+            && slot.get_item().and_then(|item| item.equipment_slot()) == Some(equipment_slot))
+    }
+
+    // the item equipped in the given equipment slot, if any
+    pub fn equipped(&self, equipment_slot: EquipmentSlot) -> Option<Item> {
+        self.equipped_index(equipment_slot)
+            .and_then(|index| self.slots[index].get_item())
+    }
+
     // try to insert an item into the inventory using the first available slot;
     // fails if there are no uncursed empty slots
     pub fn insert(&mut self, item: Item) -> bool {
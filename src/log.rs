@@ -33,6 +33,10 @@ impl Log {
         self.messages.push_front((self.turn_count, message.into()))
     }
 
+    pub fn turn(&self) -> usize {
+        self.turn_count
+    }
+
     pub fn end_turn(&mut self) {
         self.turn_count += 1
     }
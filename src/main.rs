@@ -6,7 +6,9 @@ extern crate pancurses;
 use pancurses::{Window, Input};
 
 mod byte;
+mod device;
 mod dungeon;
+mod element;
 mod fov;
 mod geometry;
 mod grid;
@@ -14,6 +16,7 @@ mod item;
 mod log;
 mod memory;
 mod monster;
+mod morgue;
 mod player;
 mod speech;
 mod spell;
@@ -24,33 +27,78 @@ mod util;
 mod view;
 mod world;
 
+use std::fmt::Display;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use world::World;
 use geometry::Point;
+use player::Ending;
 use tile::Stairs;
 use byte::BitNumber;
+use item::Kind;
 
 fn main() {
+    let options = parse_options();
+
+    // check a name given on the command line before touching the terminal
+    let name = options.name.map(|name|
+        memory::encode_player_name(&name).unwrap_or_else(|e| exit_with_error(e)));
+
     let mut world = World::new();
 
     let terminal = view::initialize();
 
+    world.player.name = match name {
+        Some(name) => name,
+        None => name_prompt(&terminal)
+    };
+
     loop {
+        // a bit flip may have brought HP back up since the last fatal blow
+        if world.player.hp > 0 {
+            world.player.death_cause = None;
+        }
+
         view::draw(&terminal, &world);
 
-        let took_turn = match get_key(&terminal, &world) {
+        // a paralyzed player loses the turn without being asked for a command
+        let paralyzed = world.player.paralysis > 0;
+        if paralyzed {
+            world.log.tell("You can't move!");
+            pancurses::napms(300);
+        }
+
+        let took_turn = paralyzed || match get_key(&terminal, &world) {
             'q' => break,
             ' ' => { world.player.show_ram = !world.player.show_ram; false },
-            '<' => { world.player.try_stairs(&mut world.log, &mut world.dungeon, Stairs::Up); true },
-            '>' => { world.player.try_stairs(&mut world.log, &mut world.dungeon, Stairs::Down); true },
-            ',' | 'g' => world.player.pick_up_item(&mut world.log, &mut world.dungeon),
+            '<' => {
+                world.player.try_stairs(&mut world.log, &mut world.dungeon,
+                    &world.item_appearance_map, Stairs::Up);
+                true
+            },
+            '>' => {
+                world.player.try_stairs(&mut world.log, &mut world.dungeon,
+                    &world.item_appearance_map, Stairs::Down);
+                true
+            },
+            ',' | 'g' => world.player.pick_up_item(&mut world.log, &mut world.dungeon,
+                &world.item_appearance_map),
             'd' => match item_prompt(&terminal, &mut world, "Drop") {
-                Some(index) => world.player.drop_item(&mut world.log, &mut world.dungeon, index),
+                Some(index) => world.player.drop_item(&mut world.log, &mut world.dungeon,
+                    &world.item_appearance_map, index),
                 None => false
             },
             'a' => match item_prompt(&terminal, &mut world, "Use") {
-                Some(index) => world.player.use_item(&mut world.log, &mut world.dungeon, index),
+                Some(index) => use_prompt(&terminal, &mut world, index),
                 None => false
             },
+            'z' => cast_prompt(&terminal, &mut world),
+            'f' => fire_prompt(&terminal, &mut world),
+            '+' => {
+                view::draw_spells(&terminal, &world);
+                get_key(&terminal, &world);
+                false
+            },
             // debug commands
             '[' => { world.player.selected = (world.player.selected + 0x3F) % 0x40; false },
             ']' => { world.player.selected = (world.player.selected + 0x01) % 0x40; false },
@@ -65,22 +113,130 @@ fn main() {
             key => {
                 // try movement commands
                 if let Some(step_direction) = key_to_direction(key) {
-                    world.player.step(&mut world.log, &mut world.dungeon, step_direction)
+                    world.player.step(&mut world.log, &mut world.dungeon,
+                        &world.item_appearance_map, step_direction)
                 } else {
                     false
                 }
             }
         };
 
+        world.settle();
+        world.update_visibility();
+
         if took_turn {
-            monster::take_turns(&mut world);
+            // nothing else happens once the player has escaped or died
+            if world.player.ending().is_none() {
+                if !timer::monsters_skip_turn(&world) {
+                    monster::take_turns(&mut world);
+                }
+                timer::tick(&mut world);
+                // only lost turns count down paralysis, and only after monsters
+                // move, so the player always gets a turn between paralyses
+                if paralyzed {
+                    world.player.paralysis -= 1;
+                }
+                memory::shake(&mut world);
+                world.settle();
+                world.update_visibility();
+            }
             world.log.end_turn()
         }
+
+        if let Some(ending) = world.player.ending() {
+            game_over(&terminal, &mut world, &options.morgue_dir, &ending);
+            break
+        }
     };
 
     pancurses::endwin();
 }
 
+const USAGE: &'static str = "usage: hexs-cellar [--name NAME] [--morgue DIRECTORY]";
+
+// command-line options
+struct Options {
+    name: Option<String>,
+    morgue_dir: PathBuf
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        name: None,
+        morgue_dir: PathBuf::from("morgue")
+    };
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-n" | "--name" => match args.next() {
+                Some(name) => options.name = Some(name),
+                None => exit_with_error(USAGE)
+            },
+            "-m" | "--morgue" => match args.next() {
+                Some(directory) => options.morgue_dir = PathBuf::from(directory),
+                None => exit_with_error(USAGE)
+            },
+            _ => exit_with_error(format!("unknown option '{}'", arg))
+        }
+    }
+
+    options
+}
+
+fn exit_with_error<E: Display>(e: E) -> ! {
+    writeln!(std::io::stderr(), "{}", e).unwrap();
+    std::process::exit(1)
+}
+
+// record the game in the morgue and show the final screen
+fn game_over(terminal: &Window, world: &mut World, morgue_dir: &Path, ending: &Ending) {
+    if let Ending::Death(ref cause) = *ending {
+        world.log.tell(format!("You were {}...", cause));
+    }
+
+    let morgue_note = match morgue::write(world, ending, morgue_dir) {
+        Ok(path) => format!("Morgue file written to {}.", path.display()),
+        Err(e) => format!("Couldn't write morgue file: {}", e)
+    };
+
+    view::draw_game_over(terminal, world, ending, &morgue_note);
+    get_key(terminal, world);
+}
+
+// ask for the player's name until we get one that fits in RAM
+fn name_prompt(terminal: &Window) -> [u8; 15] {
+    let mut name = String::new();
+    let mut error: Option<String> = None;
+
+    loop {
+        view::draw_name_entry(terminal, &name, error.take());
+
+        match terminal.getch() {
+            Some(Input::Character('\n')) | Some(Input::KeyEnter) =>
+                match memory::encode_player_name(&name) {
+                    Ok(encoded) => return encoded,
+                    Err(e) => error = Some(e.to_string())
+                },
+            Some(Input::Character('\x7f')) | Some(Input::Character('\x08'))
+                | Some(Input::KeyBackspace) => {
+                name.pop();
+            },
+            Some(Input::Character(c)) if !c.is_control() => {
+                name.push(c);
+                if let Err(e) = memory::encode_player_name(&name) {
+                    name.pop();
+                    error = Some(e.to_string());
+                }
+            },
+            Some(Input::KeyResize) => {
+                terminal.clearok(true);
+            },
+            _ => {}
+        }
+    }
+}
+
 // get a key and handle window resize events
 fn get_key(terminal: &Window, world: &World) -> char {
     loop {
@@ -140,6 +296,139 @@ fn item_prompt_rec(terminal: &Window, world: &mut World,
     })
 }
 
+// use an item, asking for whatever else a device needs;
+// returns whether this consumes a turn
+fn use_prompt(terminal: &Window, world: &mut World, index: BitNumber) -> bool {
+    let kind = world.player.inventory.slots[index as usize].get_item()
+        .map(|item| item.kind(&world.item_appearance_map));
+
+    match kind {
+        Some(kind @ Kind::Corruptor) | Some(kind @ Kind::Offsetter) | Some(kind @ Kind::Copier) =>
+            device_prompt(terminal, world, index, kind),
+        Some(Kind::WandOfDeath) =>
+            match direction_prompt(terminal, world, String::from("Zap in which direction?")) {
+                Some(direction) => world.player.zap_wand(&mut world.log, &mut world.dungeon,
+                    index, direction),
+                None => false
+            },
+        _ => world.player.use_item(&mut world.log, &mut world.dungeon,
+            &world.item_appearance_map, index)
+    }
+}
+
+// activate a device, then ask for whatever it needs; the device is
+// identified before the prompt, so backing out still takes the turn
+fn device_prompt(terminal: &Window, world: &mut World, index: BitNumber, kind: Kind) -> bool {
+    device::activate(world, index);
+
+    match kind {
+        Kind::Corruptor => device::corrupt(world),
+        Kind::Offsetter => {
+            let prompt = String::from("Set the low nibble to?");
+            if let Some(nibble) = nibble_prompt(terminal, world, prompt) {
+                device::offset(world, nibble)
+            }
+        },
+        Kind::Copier =>
+            match char_prompt(terminal, world, "Copy or paste? (c/p)") {
+                Some('c') => device::copy(world),
+                Some('p') => device::paste(world),
+                Some(_) => world.log.tell("Nothing happens."),
+                None => {}
+            },
+        _ => {}
+    }
+    true
+}
+
+// ask for a known spell (and a nibble, if needed) and cast it;
+// returns whether this consumes a turn
+fn cast_prompt(terminal: &Window, world: &mut World) -> bool {
+    let known: Vec<String> = spell::SPELLS.iter()
+        .filter(|spell| world.player.spell_memory[spell.bit() as usize])
+        .map(|spell| format!("{} {}", spell.bit() as usize + 1, spell.name()))
+        .collect();
+
+    if known.is_empty() {
+        world.log.tell("You don't know any spells.");
+        return false
+    }
+
+    let prompt = format!("Cast which spell? ({})", known.join(", "));
+    let index = match item_prompt_rec(terminal, world, prompt, true) {
+        Some(index) => index,
+        None => return false
+    };
+
+    if !world.player.spell_memory[index as usize] {
+        world.log.tell("You don't know that spell.");
+        return false
+    }
+
+    let spell = &spell::SPELLS[index as usize];
+    let nibble = if spell.needs_nibble() {
+        match nibble_prompt(terminal, world, String::from("Which nibble?")) {
+            Some(nibble) => nibble,
+            None => return false
+        }
+    } else {
+        0
+    };
+
+    spell::cast(world, spell, nibble)
+}
+
+// aim the wielded gun with a cursor and fire it;
+// returns whether this consumes a turn
+fn fire_prompt(terminal: &Window, world: &mut World) -> bool {
+    if world.player.ranged_weapon().is_none() {
+        world.log.tell("You aren't wielding anything you can fire.");
+        return false
+    }
+
+    let target = match target_prompt(terminal, world) {
+        Some(target) => target,
+        None => return false
+    };
+
+    let path = world.player.line_of_fire(&world.dungeon, target);
+    view::draw_projectile(terminal, world, &path, sprite::JELLY_GLOB);
+    world.player.fire(&mut world.log, &mut world.dungeon, &path)
+}
+
+// move a cursor over the board, starting on the nearest visible monster
+fn target_prompt(terminal: &Window, world: &mut World) -> Option<Point> {
+    world.log.tell(String::from("Fire where? (move the cursor; f or enter to fire)"));
+
+    let player_position = world.player.position;
+    let mut target = {
+        let level = world.player.current_level(&world.dungeon);
+        level.monsters.iter()
+            .filter(|monster| monster.alive() && world.player.visible.contains(&monster.position))
+            .map(|monster| monster.position)
+            .min_by_key(|&position| position.cheby_dist(player_position))
+            .unwrap_or(player_position)
+    };
+
+    loop {
+        let path = world.player.line_of_fire(&world.dungeon, target);
+        view::draw_target(terminal, world, target, &path);
+
+        match get_key(terminal, world) {
+            '\x1b' => {
+                world.log.extend_message(" Okay, then.");
+                return None
+            },
+            'f' | '\n' => return Some(target),
+            key => if let Some(direction) = key_to_direction(key) {
+                if grid::RECTANGLE.contains(target + direction) {
+                    target = target + direction;
+                }
+            }
+        }
+    }
+}
+
 fn byte_prompt(terminal: &Window, world: &mut World) -> Option<u8> {
     nibble_prompt(terminal, world, String::from("High nibble:")).and_then(|h| {
         nibble_prompt(terminal, world, String::from("Low nibble: ")).and_then(|l| {
@@ -161,6 +450,16 @@ fn nibble_prompt(terminal: &Window, world: &mut World, prompt: String) -> Option
     })
 }
 
+fn direction_prompt(terminal: &Window, world: &mut World, prompt: String) -> Option<Point>
+{
+    char_prompt(terminal, world, &prompt).and_then(|key| {
+        match key_to_direction(key) {
+            Some(direction) => Some(direction),
+            None => direction_prompt(terminal, world, prompt),
+        }
+    })
+}
+
 fn char_prompt(terminal: &Window, world: &mut World, prompt: &str) -> Option<char> {
     world.log.tell(String::from(prompt));
     view::draw(terminal, world);
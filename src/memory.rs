@@ -1,8 +1,11 @@
+use std::fmt;
 use std::mem::transmute;
+use byte::{self, BitNumber};
+use element::Element::*;
 use world::World;
 use geometry::Point;
 use sprite::Sprite;
-use util;
+use util::{self, random_range};
 
 // In Hex's Cellar, the player's spells manipulate an u8[40] of bytes that
 // affect the world around her. This file gives a "RAM map" for that array.
@@ -24,7 +27,7 @@ pub const MONSTER_HP: u8 = 2;
 // An 8-bit bitmask (there are eight spells).
 pub const SPELL_MEMORY: u8 = 0x1f;
 
-// A 32-bit bitmask (there are thirty-two items).
+// A 32-bit little-endian bitmask, one bit per item appearance.
 pub const IDENTIFICATION: u8 = 0x20;
 
 // Begins a u8[4]: one byte for each timer (poison, haste, charge, protect).
@@ -36,7 +39,7 @@ pub const INVENTORY: u8 = 0x28;
 // Same as player appearance.
 pub const DOOR_APPEARANCE: u8 = 0x30;
 pub const WALL_APPEARANCE: u8 = 0x31;
-pub const FLOOR_COLOR: u8 = 0x32;
+pub const FLOOR_APPEARANCE: u8 = 0x32;
 
 // u8 to add/subtract from depth when stairs are used; normally 1.
 pub const STAIRS_DELTA: u8 = 0x33;
@@ -47,8 +50,11 @@ pub const TIMER_DELTA: u8 = 0x34;
 // s8 to add to each damage roll; normally 0x00.
 pub const DAMAGE_OFFSET: u8 = 0x35;
 
-// ??? = 0x36;
-// ??? = 0x37;
+// u8 radius of the player's field of view; normally 4.
+pub const LIGHT_RADIUS: u8 = 0x36;
+
+// u8 chance out of 256 that a random bit flips each turn; normally 0.
+pub const INSTABILITY: u8 = 0x37;
 
 // The higher this is, the more text gets screwed up.
 pub const TEXT_SYNC: u8 = 0x38;
@@ -56,6 +62,8 @@ pub const TEXT_SYNC: u8 = 0x38;
 // Player stats.
 pub const PLAYER_HP: u8 = 0x39;
 pub const PLAYER_TP: u8 = 0x3a;
+// Def and the resistances are signed nibbles; equipment adds to them while
+// worn (see Player::update_equipment_bonus).
 pub const PLAYER_XLDEF: u8 = 0x3b; // hi-bits XL, lo-bits Def
 pub const PLAYER_POSITION: u8 = 0x3c;
 pub const PLAYER_DEPTH: u8 = 0x3d;
@@ -71,6 +79,10 @@ pub fn peek(world: &World, address: u8) -> u8 {
         _ if address >= PLAYER_NAME && address < MONSTERS =>
             world.player.name[(address - PLAYER_NAME) as usize],
 
+        // level 0 has no monsters; see World::settle
+        _ if address >= MONSTERS && address < SPELL_MEMORY && world.player.depth == 0 =>
+            0,
+
         _ if address >= MONSTERS && address < SPELL_MEMORY => {
             let monster = &world.current_level()
                 .monsters[(address - MONSTERS) as usize / 3];
@@ -91,8 +103,7 @@ pub fn peek(world: &World, address: u8) -> u8 {
                 .map(|(index, &known)| (known as u8) << index).sum(),
 
         _ if address >= IDENTIFICATION && address < TIMERS =>
-            // TODO: implement identification
-            0,
+            (world.player.identification >> (8 * (address - IDENTIFICATION))) as u8,
 
         _ if address >= TIMERS && address < INVENTORY =>
             world.player.timer[(address - TIMERS) as usize],
@@ -106,9 +117,8 @@ pub fn peek(world: &World, address: u8) -> u8 {
         WALL_APPEARANCE =>
             world.wall_appearance_byte,
 
-        FLOOR_COLOR =>
-            // TODO: remove floor color
-            0,
+        FLOOR_APPEARANCE =>
+            world.floor_appearance_byte,
 
         STAIRS_DELTA  =>
             world.player.stairs_delta,
@@ -119,13 +129,11 @@ pub fn peek(world: &World, address: u8) -> u8 {
         DAMAGE_OFFSET =>
             unsafe { transmute(world.player.damage_offset) },
 
-        0x36 =>
-            // TODO: ???
-            0,
+        LIGHT_RADIUS =>
+            world.light_radius_byte,
 
-        0x37 =>
-            // TODO: ???
-            0,
+        INSTABILITY =>
+            world.instability_byte,
 
         TEXT_SYNC =>
             world.player.text_sync,
@@ -137,7 +145,7 @@ pub fn peek(world: &World, address: u8) -> u8 {
             world.player.tp,
 
         PLAYER_XLDEF =>
-            (world.player.xl << 4) | unsafe { transmute::<i8,u8>(world.player.def) },
+            (world.player.xl << 4) | (unsafe { transmute::<i8,u8>(world.player.def) } & 0x0f),
 
         PLAYER_POSITION =>
             world.player.position.as_byte(),
@@ -146,17 +154,15 @@ pub fn peek(world: &World, address: u8) -> u8 {
             world.player.depth,
 
         METAL_ACID_RESISTANCE =>
-            // TODO: use element names
             unsafe {
-                transmute((world.player.aptitude[0] << 4)
-                    | (world.player.aptitude[1] & 0x0f))
+                transmute((world.player.aptitude[Metal as usize] << 4)
+                    | (world.player.aptitude[Acid as usize] & 0x0f))
             },
 
         FIRE_ELEC_RESISTANCE =>
-            // TODO: use element names
             unsafe {
-                transmute((world.player.aptitude[2] << 4)
-                    | (world.player.aptitude[3] & 0x0f))
+                transmute((world.player.aptitude[Fire as usize] << 4)
+                    | (world.player.aptitude[Elec as usize] & 0x0f))
             },
 
         _ => panic!("memory::peek - invalid address {}", address)
@@ -176,6 +182,9 @@ pub fn poke(world: &mut World, address: u8, value: u8) {
         _ if address >= PLAYER_NAME && address < MONSTERS =>
             world.player.name[(address - PLAYER_NAME) as usize] = value,
 
+        _ if address >= MONSTERS && address < SPELL_MEMORY && world.player.depth == 0 =>
+            {},
+
         _ if address >= MONSTERS && address < SPELL_MEMORY => {
             let monster = &mut world.current_level_mut()
                 .monsters[(address - MONSTERS) as usize / 3];
@@ -198,9 +207,11 @@ pub fn poke(world: &mut World, address: u8, value: u8) {
                 *known = value & (1 << index) != 0
             },
 
-        _ if address >= IDENTIFICATION && address < TIMERS =>
-            // TODO: implement identification
-            {},
+        _ if address >= IDENTIFICATION && address < TIMERS => {
+            let shift = 8 * (address - IDENTIFICATION);
+            world.player.identification = (world.player.identification & !(0xff << shift))
+                | (value as u32) << shift
+        },
 
         _ if address >= TIMERS && address < INVENTORY =>
             world.player.timer[(address - TIMERS) as usize] = value,
@@ -214,9 +225,8 @@ pub fn poke(world: &mut World, address: u8, value: u8) {
         WALL_APPEARANCE =>
             world.wall_appearance_byte = value,
 
-        FLOOR_COLOR =>
-            // TODO: remove floor color
-            {},
+        FLOOR_APPEARANCE =>
+            world.floor_appearance_byte = value,
 
         STAIRS_DELTA  =>
             world.player.stairs_delta = value,
@@ -227,13 +237,11 @@ pub fn poke(world: &mut World, address: u8, value: u8) {
         DAMAGE_OFFSET =>
             world.player.damage_offset = unsafe { transmute(value) },
 
-        0x36 =>
-            // TODO: ???
-            {},
+        LIGHT_RADIUS =>
+            world.light_radius_byte = value,
 
-        0x37 =>
-            // TODO: ???
-            {},
+        INSTABILITY =>
+            world.instability_byte = value,
 
         TEXT_SYNC =>
             world.player.text_sync = value,
@@ -256,23 +264,40 @@ pub fn poke(world: &mut World, address: u8, value: u8) {
             world.player.depth = value,
 
         METAL_ACID_RESISTANCE => {
-            // TODO: use element names
             // note: transmute before shift for sign-extension
-            world.player.aptitude[0] = unsafe { transmute::<u8, i8>(value) } >> 4;
-            world.player.aptitude[1] = upcast_i4(value)
+            world.player.aptitude[Metal as usize] = unsafe { transmute::<u8, i8>(value) } >> 4;
+            world.player.aptitude[Acid as usize] = upcast_i4(value)
         },
 
         FIRE_ELEC_RESISTANCE => {
-            // TODO: use element names
             // note: transmute before shift for sign-extension
-            world.player.aptitude[2] = unsafe { transmute::<u8, i8>(value) } >> 4;
-            world.player.aptitude[3] = upcast_i4(value)
+            world.player.aptitude[Fire as usize] = unsafe { transmute::<u8, i8>(value) } >> 4;
+            world.player.aptitude[Elec as usize] = upcast_i4(value)
         },
 
         _ => panic!("memory::poke - invalid address")
     }
 }
 
+// flip `count` random bits anywhere in RAM
+pub fn flip_random_bits(world: &mut World, count: usize) {
+    for _ in 0..count {
+        let address = random_range(0x00..0x40);
+        let bit = BitNumber::from_number(random_range(0..8));
+        let value = peek(world, address);
+        poke(world, address, byte::flip(value, bit));
+    }
+}
+
+// called at the end of each turn: the higher the instability byte,
+// the more likely it is that something in RAM gets knocked loose
+pub fn shake(world: &mut World) {
+    if random_range(0..256) < world.instability_byte as i32 {
+        world.log.tell("The cellar shudders. Something shifts in memory.");
+        flip_random_bits(world, 1);
+    }
+}
+
 // pretend the low four bits of our u8 argument are an "i4" and sign-extend to i8
 fn upcast_i4(the_i4: u8) -> i8 {
     (unsafe { transmute::<u8, i8>(the_i4) } << 4) >> 4
@@ -291,7 +316,7 @@ fn report_player_appearance_change(world: &mut World, old: Sprite, new: Sprite)
     }
 }
 
-const CP437: &'static [char; 256] = &[
+pub const CP437: &'static [char; 256] = &[
     ' ', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼',
     '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
     ' ', '!', '"', '#', '$', '%', '&', '\'','(', ')', '*', '+', ',', '-', '.', '/',
@@ -321,3 +346,49 @@ pub fn player_name(world: &World) -> String {
     }
     return name;
 }
+
+// The name has to leave room for its zero terminator.
+pub const MAX_NAME_LENGTH: usize = 14;
+
+pub enum NameError {
+    Empty,
+    TooLong,
+    BadCharacter(char) // no CP437 glyph for this character
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NameError::Empty =>
+                write!(f, "name error: name is empty"),
+            NameError::TooLong =>
+                write!(f, "name error: name is longer than {} characters", MAX_NAME_LENGTH),
+            NameError::BadCharacter(c) =>
+                write!(f, "name error: can't write '{}' in CP437", c),
+        }
+    }
+}
+
+// encode a name as zero-terminated CP437, ready for Player::name
+pub fn encode_player_name(name: &str) -> Result<[u8; 15], NameError> {
+    let mut encoded = [0; 15];
+    let mut length = 0;
+
+    for c in name.chars() {
+        if length >= MAX_NAME_LENGTH {
+            return Err(NameError::TooLong)
+        }
+        // skip 0x00, which would end the name early
+        encoded[length] = match CP437.iter().skip(1).position(|&glyph| glyph == c) {
+            Some(index) => index as u8 + 1,
+            None => return Err(NameError::BadCharacter(c))
+        };
+        length += 1;
+    }
+
+    if length == 0 {
+        Err(NameError::Empty)
+    } else {
+        Ok(encoded)
+    }
+}
@@ -1,14 +1,21 @@
 use sprite::*;
+use std::cmp::{max, min};
 use std::mem;
-use util;
+use util::{self, random_range, roll};
+use element::Element::{self, *};
+use memory;
+use timer::Timer;
 use geometry::*;
 use rand::{Rng, thread_rng};
 use grid;
 use dungeon::Level;
 use world::World;
 use speech;
+use item::EquipmentSlot;
+use tile::Tile;
+use self::Behavior::*;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq)]
 pub enum Kind {
     Kestrel = 0x0,
     Skeleton = 0x1,
@@ -33,25 +40,52 @@ pub struct Info {
     sprite: Sprite,
     habitat: (u8, u8),
     max_hp: u8,
+    def: u8,
+    damage: (i32, i32), // melee damage dice: (count, sides)
+    element: Option<Element>,
+    behavior: Behavior,
+}
+
+// What a species does besides walking up to the player and hitting them.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Behavior {
+    Plain,
+    Regenerate, // heals every turn
+    Spit,       // spits from a distance
+    Burn,       // hits poison
+    Paralyze,   // hits stop the player moving, unless they resist elec
+    Smash,      // breaks through walls in its way
+    Glitch,     // hits flip bits
+    Drain,      // hits can drain XL
+    Shoot,      // keeps its distance and shoots
+    Summon,     // calls allies into dead monster slots
+    Attract,    // pulls metal items, and the player by what metal they carry
+    Stationary, // never moves; blasts everything around the player
+    Incinerate, // hits kill outright
 }
 
+// how far spitters can spit, turrets can blast and attractors can pull
+const SPIT_RANGE: i32 = 4;
+const BLAST_RANGE: i32 = 4;
+const ATTRACT_RANGE: i32 = 5;
+
 pub const INFOS: [Info; 16] = [
-    Info {name: "kestrel",       sprite: Sprite {character: 'K', color: WHITE},  habitat: ( 1,   4), max_hp: 6},
-    Info {name: "skeleton",      sprite: Sprite {character: 'Z', color: GRAY},   habitat: ( 1,   5), max_hp: 8},
-    Info {name: "troll",         sprite: Sprite {character: 'T', color: BROWN},  habitat: ( 2,   6), max_hp: 20},
-    Info {name: "android",       sprite: Sprite {character: 'A', color: TEAL},   habitat: ( 3,   7), max_hp: 15},
-    Info {name: "jelly",         sprite: Sprite {character: 'J', color: LIME},   habitat: ( 4,   8), max_hp: 13},
-    Info {name: "salamander",    sprite: Sprite {character: 'S', color: RED},    habitat: ( 5,   9), max_hp: 18},
-    Info {name: "tiny UFO",      sprite: Sprite {character: 'U', color: AQUA},   habitat: ( 6,  10), max_hp: 16},
-    Info {name: "minotaur",      sprite: Sprite {character: 'M', color: MAROON}, habitat: ( 8,  15), max_hp: 40},
-    Info {name: "glitch",        sprite: Sprite {character: 'B', color: GLITCH}, habitat: ( 0,   0), max_hp: 15},
-    Info {name: "witch",         sprite: Sprite {character: 'W', color: PURPLE}, habitat: (11,  16), max_hp: 24},
-    Info {name: "ghost",         sprite: Sprite {character: 'G', color: DARK},   habitat: (13,  19), max_hp: 35},
-    Info {name: "soldier",       sprite: Sprite {character: '@', color: BLUE},   habitat: (14,  20), max_hp: 45},
-    Info {name: "attractor",     sprite: Sprite {character: '8', color: TEAL},   habitat: (15,  20), max_hp: 50},
-    Info {name: "turret",        sprite: Sprite {character: '9', color: DARK},   habitat: (15,  20), max_hp: 60},
-    Info {name: "elf",           sprite: Sprite {character: 'E', color: LIME},   habitat: ( 1,   0), max_hp: 40},
-    Info {name: "golden dragon", sprite: Sprite {character: 'D', color: GOLD},   habitat: (20, 255), max_hp: 200},
+    Info {name: "kestrel",       sprite: Sprite {character: 'K', color: WHITE},  habitat: ( 1,   4), max_hp:   6, def: 0, damage: (1, 2), element: None,        behavior: Plain},
+    Info {name: "skeleton",      sprite: Sprite {character: 'Z', color: GRAY},   habitat: ( 1,   5), max_hp:   8, def: 1, damage: (1, 4), element: None,        behavior: Plain},
+    Info {name: "troll",         sprite: Sprite {character: 'T', color: BROWN},  habitat: ( 2,   6), max_hp:  20, def: 1, damage: (2, 3), element: None,        behavior: Regenerate},
+    Info {name: "android",       sprite: Sprite {character: 'A', color: TEAL},   habitat: ( 3,   7), max_hp:  15, def: 3, damage: (2, 3), element: Some(Metal), behavior: Plain},
+    Info {name: "jelly",         sprite: Sprite {character: 'J', color: LIME},   habitat: ( 4,   8), max_hp:  13, def: 0, damage: (1, 4), element: Some(Acid),  behavior: Spit},
+    Info {name: "salamander",    sprite: Sprite {character: 'S', color: RED},    habitat: ( 5,   9), max_hp:  18, def: 1, damage: (2, 3), element: Some(Fire),  behavior: Burn},
+    Info {name: "tiny UFO",      sprite: Sprite {character: 'U', color: AQUA},   habitat: ( 6,  10), max_hp:  16, def: 1, damage: (1, 4), element: Some(Elec),  behavior: Paralyze},
+    Info {name: "minotaur",      sprite: Sprite {character: 'M', color: MAROON}, habitat: ( 8,  15), max_hp:  40, def: 3, damage: (3, 4), element: None,        behavior: Smash},
+    Info {name: "glitch",        sprite: Sprite {character: 'B', color: GLITCH}, habitat: ( 0,   0), max_hp:  15, def: 0, damage: (1, 4), element: None,        behavior: Glitch},
+    Info {name: "witch",         sprite: Sprite {character: 'W', color: PURPLE}, habitat: (11,  16), max_hp:  24, def: 1, damage: (2, 3), element: None,        behavior: Summon},
+    Info {name: "ghost",         sprite: Sprite {character: 'G', color: DARK},   habitat: (13,  19), max_hp:  35, def: 2, damage: (2, 4), element: None,        behavior: Drain},
+    Info {name: "soldier",       sprite: Sprite {character: '@', color: BLUE},   habitat: (14,  20), max_hp:  45, def: 3, damage: (2, 4), element: Some(Metal), behavior: Shoot},
+    Info {name: "attractor",     sprite: Sprite {character: '8', color: TEAL},   habitat: (15,  20), max_hp:  50, def: 4, damage: (2, 3), element: Some(Metal), behavior: Attract},
+    Info {name: "turret",        sprite: Sprite {character: '9', color: DARK},   habitat: (15,  20), max_hp:  60, def: 5, damage: (3, 3), element: Some(Elec),  behavior: Stationary},
+    Info {name: "elf",           sprite: Sprite {character: 'E', color: LIME},   habitat: ( 1,   0), max_hp:  40, def: 2, damage: (2, 4), element: None,        behavior: Plain},
+    Info {name: "golden dragon", sprite: Sprite {character: 'D', color: GOLD},   habitat: (20, 255), max_hp: 200, def: 8, damage: (6, 6), element: Some(Fire),  behavior: Incinerate},
 ];
 
 #[derive(Copy, Clone)]
@@ -82,24 +116,28 @@ impl Monster {
         }
     }
 
-    pub fn generate(depth: u8, position: Point) -> Monster {
-        let infos = &INFOS;
-        let (kind, info) = util::pick((0..16u8).zip(infos)
-            .filter(|&(_, ref info)| habitable(info, depth)));
-
+    pub fn new(kind: Kind, position: Point) -> Monster {
         Monster {
-            kind: unsafe { mem::transmute(kind) },
+            kind: kind,
             charged:    false,
             vulnerable: false,
             venomous:   false,
             corrupted:  false,
             position: position,
-            hp: info.max_hp,
+            hp: INFOS[kind as usize].max_hp,
 
             alert: false
         }
     }
 
+    pub fn generate(depth: u8, position: Point) -> Monster {
+        let infos = &INFOS;
+        let (kind, _) = util::pick((0..16u8).zip(infos)
+            .filter(|&(_, ref info)| habitable(info, depth)));
+
+        Monster::new(unsafe { mem::transmute(kind) }, position)
+    }
+
     pub fn sprite(&self) -> Sprite {
         INFOS[self.kind as usize].sprite
     }
@@ -108,6 +146,15 @@ impl Monster {
         INFOS[self.kind as usize].name
     }
 
+    // damage is reduced by this much, unless the monster is vulnerable
+    pub fn defense(&self) -> u8 {
+        if self.vulnerable { 0 } else { INFOS[self.kind as usize].def }
+    }
+
+    pub fn element(&self) -> Option<Element> {
+        INFOS[self.kind as usize].element
+    }
+
     pub fn alive(&self) -> bool {
         self.hp > 0
     }
@@ -120,43 +167,354 @@ fn habitable(info: &Info, depth: u8) -> bool {
 
 
 pub fn take_turns(world: &mut World) {
-    let player = &world.player;
-    let mut level = player.current_level_mut(&mut world.dungeon);
-
     // filter to get only living monsters;
     // score monsters by distance to player so that further monsters won't get
     // stuck behind closer monsters that haven't moved.
-    let mut turn_order: Vec<(usize, (i32, i32))> =
-        level.monsters.iter().enumerate().flat_map(|(i, m)|
+    let mut turn_order: Vec<(usize, (i32, i32))> = {
+        let player = &world.player;
+        world.current_level().monsters.iter().enumerate().flat_map(|(i, m)|
             if m.alive() {
                 Some( (i, (m.position.cheby_dist(player.position),
                            m.position.taxi_dist(player.position))) )
             } else {
                 None
-            }).collect();
+            }).collect()
+    };
     turn_order.sort_by_key(|&(_, score)| score);
 
+    let depth = world.player.depth;
     for (monster_index, _) in turn_order {
-        if level.monsters[monster_index].alert {
+        // a corrupted monster's attack can send the player off the board or
+        // to another level; the rest of the monsters wait until World::settle
+        // is done
+        if world.player.depth != depth || !grid::RECTANGLE.contains(world.player.position) {
+            break
+        }
+
+        // ...or flip a later monster's HP to 0, or its position off the board
+        let monster = world.current_level().monsters[monster_index];
+        if monster.alive() && grid::RECTANGLE.contains(monster.position) {
+            take_turn(world, monster_index);
+        }
+    }
+}
+
+fn take_turn(world: &mut World, monster_index: usize) {
+    let player_position = world.player.position;
+    let monster = world.current_level().monsters[monster_index];
+    let info = &INFOS[monster.kind as usize];
+    let in_view = world.player.visible.contains(&monster.position);
+    let distance = monster.position.cheby_dist(player_position);
 
-            if level.monsters[monster_index].position.cheby_dist(player.position) == 1 {
-                // TODO: attack
-            } else if player.visible.contains(&level.monsters[monster_index].position) {
-                approach(level, monster_index, player.position);
+    if info.behavior == Regenerate && monster.hp < info.max_hp {
+        world.current_level_mut().monsters[monster_index].hp += 1;
+    }
+
+    if !monster.alert {
+        if in_view {
+            // TODO: alert stuff nearby? Maybe some monsters are loud, and some aren't -- the shout_lines should make it obvious.
+            world.log.tell(speech::shout_line(monster.kind));
+            world.current_level_mut().monsters[monster_index].alert = true;
+        }
+        return
+    }
+
+    if info.behavior == Attract {
+        attract(world, monster_index, in_view && distance <= ATTRACT_RANGE);
+    }
+
+    // summoning takes the witch's turn, if anyone answers
+    if info.behavior == Summon && in_view && random_range(0..3) == 0
+        && summon(world, monster_index)
+    {
+        return
+    }
+
+    match info.behavior {
+        Stationary => if in_view && distance <= BLAST_RANGE {
+            blast(world, monster_index);
+        },
+
+        Spit if in_view && distance > 1 && distance <= SPIT_RANGE
+            && clear_shot(world.current_level(), monster.position, player_position) =>
+            attack(world, monster_index, "spits at"),
+
+        Shoot if in_view && distance > 1
+            && clear_shot(world.current_level(), monster.position, player_position) =>
+            attack(world, monster_index, "shoots"),
+
+        // soldiers back off to get a clear shot, and only fight up close if cornered
+        Shoot if distance == 1 => if !retreat(world.current_level_mut(), monster_index, player_position) {
+            attack(world, monster_index, "hits");
+        },
+
+        // smashers don't need to see the player to come straight for them
+        _ => if distance == 1 {
+            attack(world, monster_index, "hits");
+        } else if in_view || info.behavior == Smash {
+            let moved = approach(world.current_level_mut(), monster_index, player_position);
+            if !moved && info.behavior == Smash {
+                smash(world, monster_index, player_position);
             }
+        }
+    }
+}
 
-        } else {
+// attack on the player, with damage modified by the monster's flags and
+// reduced by the player's defense, aptitude and protect timer; the verb
+// describes how the attack lands ("hits", "shoots", ...)
+fn attack(world: &mut World, monster_index: usize, verb: &str) {
+    let monster = world.current_level().monsters[monster_index];
+    let info = &INFOS[monster.kind as usize];
 
-            let mut monster = &mut level.monsters[monster_index];
+    if info.behavior == Incinerate {
+        world.log.tell(format!("The {} incinerates you!", monster.name()));
+        let hp = world.player.hp as i32;
+        world.player.hurt(hp, format!("incinerated by {}", util::a_or_an(monster.name())));
+        return
+    }
 
-            if player.visible.contains(&monster.position) {
-                // TODO: alert stuff nearby? Maybe some monsters are loud, and some aren't -- the shout_lines should make it obvious.
-                world.log.tell(speech::shout_line(monster.kind));
-                monster.alert = true;
+    let (count, sides) = info.damage;
+    let mut damage = roll(count, sides);
+    if monster.charged {
+        damage *= 2;
+    }
+    if monster.corrupted {
+        damage *= 2;
+    }
+    damage -= world.player.def as i32;
+    if let Some(element) = info.element {
+        damage -= world.player.aptitude[element as usize] as i32;
+    }
+    damage = world.player.protect_damage(damage);
+
+    if damage <= 0 {
+        world.log.tell(format!("The {} misses you.", monster.name()));
+        return
+    }
+
+    world.player.hurt(damage, format!("killed by {}", util::a_or_an(monster.name())));
+    world.log.tell(format!("The {} {} you for {} damage.", monster.name(), verb, damage));
+
+    if info.behavior == Burn {
+        world.log.tell("The burn festers!");
+    }
+    if monster.venomous || info.behavior == Burn {
+        let poison = &mut world.player.timer[Timer::Poison as usize];
+        *poison = max(*poison, 8);
+    }
+
+    match info.behavior {
+        Paralyze if world.player.aptitude[Elec as usize] <= 0
+            && world.player.paralysis == 0 && random_range(0..3) == 0 => {
+            world.log.tell("The shock paralyzes you!");
+            world.player.paralysis = 2; // the player's next two turns
+        },
+        Glitch => {
+            world.log.tell("Reality stutters around you!");
+            memory::flip_random_bits(world, 3);
+        },
+        Drain if world.player.xl > 0 && random_range(0..3) == 0 => {
+            world.log.tell("You feel your life force draining away!");
+            world.player.lose_levels(1);
+        },
+        _ => {}
+    }
+
+    if monster.corrupted {
+        world.log.tell("Your memory flickers!");
+        memory::flip_random_bits(world, 2);
+    }
+}
+
+// Call an ally into a dead monster slot next to the summoner; returns if one
+// came. Summons show up in RAM, where a quick poke can undo them.
+fn summon(world: &mut World, monster_index: usize) -> bool {
+    let depth = world.player.depth;
+    let player_position = world.player.position;
+
+    let (slot, spots) = {
+        let level = world.current_level();
+        let summoner = level.monsters[monster_index].position;
+        let slot = match level.monsters.iter().position(|m| !m.alive()) {
+            Some(slot) => slot,
+            None => return false
+        };
+        let spots: Vec<Point> = Rectangle::point(summoner).grow(1).into_iter()
+            .filter(|&p| grid::RECTANGLE.contains(p)
+                && p != player_position
+                && level.tiles[p].is_open()
+                && level.monster_at(p).is_none())
+            .collect();
+        (slot, spots)
+    };
+
+    if spots.is_empty() {
+        return false
+    }
+
+    let mut ally = Monster::generate(depth, util::pick(spots));
+    // even a witch can't call up a dragon
+    if ally.kind == Kind::GoldenDragon {
+        return false
+    }
+    ally.alert = true;
+
+    let summoner = world.current_level().monsters[monster_index];
+    world.current_level_mut().monsters[slot] = ally;
+    world.log.tell(format!("The {} convokes {}!", summoner.name(), util::a_or_an(ally.name())));
+    true
+}
+
+// Pull metal toward an attractor: floor items in range slide a tile closer,
+// and a player it reaches may have their metal weapon yanked away or be
+// dragged along by their metal armor, unless their metal aptitude holds.
+fn attract(world: &mut World, monster_index: usize, reaches_player: bool) {
+    let attractor = world.current_level().monsters[monster_index];
+    let center = attractor.position;
+
+    let pulls: Vec<(Point, Point)> = world.current_level().items.iter()
+        .filter(|&(&p, item)| item.is_metal() && p != center && p.cheby_dist(center) <= ATTRACT_RANGE)
+        .map(|(&p, _)| (p, p.step_towards(center)))
+        .collect();
+
+    for (from, to) in pulls {
+        let level = world.player.current_level_mut(&mut world.dungeon);
+        if grid::RECTANGLE.contains(to)
+            && level.tiles[to].is_open() && !level.items.contains_key(&to) {
+            let item = level.items.remove(&from).unwrap();
+            level.items.insert(to, item);
+            if world.player.visible.contains(&from) {
+                world.log.tell(format!("The {} slides toward the {}!",
+                    item.name(world.player.identification, &world.item_appearance_map),
+                    attractor.name()));
+            }
+        }
+    }
+
+    // metal aptitude 4 or more always holds on
+    let metal = world.player.aptitude[Metal as usize] as i32;
+    if !reaches_player || random_range(0..16) >= 4 - metal {
+        return
+    }
+
+    if let Some(index) = world.player.inventory.equipped_index(EquipmentSlot::Weapon) {
+        let slot = world.player.inventory.slots[index];
+        let weapon = slot.get_item().unwrap();
+        if weapon.is_metal() {
+            let name = weapon.name(world.player.identification, &world.item_appearance_map);
+            if slot.is_cursed() {
+                world.log.tell(format!("The {} tugs at your cursed {}, but it won't budge!",
+                    attractor.name(), name));
+                return
+            }
+
+            // it lands at the attractor's feet, or as close as it can
+            let spot = Rectangle::point(center).grow(1).into_iter()
+                .filter(|&p| grid::RECTANGLE.contains(p)
+                    && world.current_level().tiles[p].is_open()
+                    && !world.current_level().items.contains_key(&p))
+                .min_by_key(|&p| p.cheby_dist(center));
+            if let Some(spot) = spot {
+                world.player.lose_item(index);
+                world.current_level_mut().items.insert(spot, weapon);
+                world.log.tell(format!("The {} yanks the {} out of your hands!",
+                    attractor.name(), name));
+                return
+            }
+        }
+    }
+
+    let armor = [EquipmentSlot::Body, EquipmentSlot::Neck].iter()
+        .filter_map(|&slot| world.player.inventory.equipped(slot))
+        .find(|item| item.is_metal());
+    if let Some(armor) = armor {
+        let to = world.player.position.step_towards(center);
+        let level = world.current_level();
+        if to != center && grid::RECTANGLE.contains(to)
+            && level.tiles[to].is_open() && level.monster_at(to).is_none() {
+            world.player.position = to;
+            world.log.tell(format!("The {} drags you toward it by your {}!", attractor.name(),
+                armor.name(world.player.identification, &world.item_appearance_map)));
+        }
+    }
+}
+
+// a turret's blast hits the player and every monster next to them
+fn blast(world: &mut World, monster_index: usize) {
+    let center = world.player.position;
+    let depth = world.player.depth;
+    attack(world, monster_index, "blasts");
+
+    // the blast may have flipped the player out of the level
+    if world.player.depth != depth {
+        return
+    }
+
+    let (count, sides) = INFOS[Kind::Turret as usize].damage;
+    let level = world.player.current_level_mut(&mut world.dungeon);
+    for (index, monster) in level.monsters.iter_mut().enumerate() {
+        if index != monster_index && monster.alive() && monster.position.cheby_dist(center) <= 1 {
+            let damage = roll(count, sides) - monster.defense() as i32;
+            if damage > 0 {
+                monster.hp = monster.hp.saturating_sub(min(damage, 0xff) as u8);
+                if !monster.alive() {
+                    world.log.tell(format!("The blast destroys the {}!", monster.name()));
+                }
             }
+        }
+    }
+}
 
+// whether a shot from one point to another would get there unobstructed
+fn clear_shot(level: &Level, from: Point, to: Point) -> bool {
+    let path = from.line_to(to);
+    path.iter().take(path.len().saturating_sub(1)).all(|&p|
+        grid::RECTANGLE.contains(p) && level.tiles[p].is_open() && level.monster_at(p).is_none())
+}
+
+// step to any open spot further from `threat`; returns if the monster moved
+fn retreat(level: &mut Level, monster_index: usize, threat: Point) -> bool {
+    let current = level.monsters[monster_index].position;
+
+    let mut choices: Vec<Point> = Rectangle::point(current).grow(1).into_iter()
+        .filter(|&p| p.cheby_dist(threat) > current.cheby_dist(threat))
+        .collect();
+    thread_rng().shuffle(&mut choices[..]);
+
+    for point in choices {
+        if grid::RECTANGLE.contains(point)
+            && level.tiles[point].is_open()
+            && level.monster_at(point).is_none()
+        {
+            level.monsters[monster_index].position = point;
+            return true
         }
     }
+
+    false
+}
+
+// knock down the wall or door between a monster and its target
+fn smash(world: &mut World, monster_index: usize, target: Point) {
+    let monster = world.current_level().monsters[monster_index];
+    let point = monster.position.step_towards(target);
+
+    if !grid::RECTANGLE.contains(point) {
+        return
+    }
+    let obstacle = match world.current_level().tiles[point] {
+        Tile::Wall => "wall",
+        Tile::Door => "door",
+        _ => return
+    };
+
+    world.current_level_mut().tiles[point] = Tile::Floor;
+    if world.player.visible.contains(&point) {
+        world.log.tell(format!("The {} smashes through the {}!", monster.name(), obstacle));
+    } else {
+        world.log.tell("You hear a crash.");
+    }
 }
 
 
@@ -0,0 +1,74 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use memory;
+use player::Ending;
+use world::World;
+
+// Write a plain-text record of a finished game into `directory`,
+// returning the path of the new file.
+pub fn write(world: &World, ending: &Ending, directory: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(directory)?;
+
+    let name = memory::player_name(world);
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs()).unwrap_or(0);
+    // names can hold any CP437 character, so keep the filename tame
+    let safe_name: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let path = directory.join(format!("{}-{}.txt", safe_name, timestamp));
+
+    let mut file = File::create(&path)?;
+    file.write_all(report(world, ending).as_bytes())?;
+    Ok(path)
+}
+
+fn report(world: &World, ending: &Ending) -> String {
+    let player = &world.player;
+    let mut report = String::new();
+
+    let turns = world.log.turn();
+    report.push_str(&format!("{} {} after {} turn{}.\n",
+        memory::player_name(world), headline(world, ending),
+        turns, if turns == 1 { "" } else { "s" }));
+    report.push_str(&format!("Score: {} (deepest level {})\n\n",
+        player.score(), player.deepest));
+
+    report.push_str(&format!("HP {}  TP {}  XL {}  Def {}\n\n",
+        player.hp, player.tp, player.xl, player.def));
+
+    report.push_str("RAM:\n");
+    for row in 0..8u8 {
+        report.push_str(&format!("{:02x}:", row * 8));
+        for column in 0..8u8 {
+            report.push_str(&format!(" {:02x}", memory::peek(world, row * 8 + column)));
+        }
+        report.push('\n');
+    }
+
+    report.push_str("\nInventory:\n");
+    for (index, slot) in player.inventory.slots.iter().enumerate() {
+        report.push_str(&format!("{} {}\n", index + 1,
+            slot.description(player.identification, &world.item_appearance_map)));
+    }
+
+    report.push_str("\nLast messages:\n");
+    let messages: Vec<_> = world.log.recent_messages().iter().take(20).collect();
+    for &&(turn, ref message) in messages.iter().rev() {
+        report.push_str(&format!("[{}] {}\n", turn, message));
+    }
+
+    report
+}
+
+// e.g. "was killed by a troll on level 3"; follows the player's name
+pub fn headline(world: &World, ending: &Ending) -> String {
+    match *ending {
+        Ending::Death(ref cause) =>
+            format!("was {} on level {}", cause, world.player.depth),
+        Ending::Escape =>
+            String::from("escaped the cellar with the golden pendant"),
+    }
+}
@@ -1,15 +1,34 @@
+use std::cmp::{max, min};
 use std::collections::HashSet;
 use byte;
 use byte::BitNumber;
 use dungeon::{Dungeon, Level};
+use element::Element;
 use geometry::Point;
 use grid;
 use tile::{Tile, Stairs};
-use fov;
 use log::Log;
-use util::a_or_an;
-use item::{Inventory, InventorySlot};
+use util::{a_or_an, pick, roll};
+use item::{self, Appearance, AppearanceMap, EquipmentSlot, Inventory, InventorySlot, Item, Kind};
+use monster::{self, Monster};
 use speech;
+use spell::{self, Spell};
+use timer::Timer;
+
+// turns a timer runs for after an ordinary pill; doubled when enchanted
+const PILL_DURATION: u8 = 10;
+
+// turns the palantir shows the whole level for
+const SCRYING_DURATION: u8 = 20;
+
+// XL shares a byte with defense, so it fits in a nibble
+const MAX_XL: u8 = 15;
+
+// How the game ended.
+pub enum Ending {
+    Death(String), // cause of death, e.g. "killed by a troll"
+    Escape,        // climbed out of the cellar with the golden pendant
+}
 
 pub struct Player {
     pub position: Point,
@@ -23,18 +42,48 @@ pub struct Player {
     // Index with element::Element.
     pub aptitude: [i8; 4],
 
+    // The part of (def, aptitude) that comes from equipment; see
+    // Player::update_equipment_bonus. Not in RAM.
+    pub equipment_bonus: (i8, [i8; 4]),
+
     // Index with byte::BitNumber.
     pub inventory: Inventory,
 
     // Index with byte::BitNumber.
     pub spell_memory: [bool; 8],
 
+    // One bit per item::Appearance; set bits show the item's true name.
+    pub identification: u32,
+
     // Index with timer::Timer.
     pub timer: [u8; 4],
 
+    // Which timers timer::tick has announced as started. Index with timer::Timer.
+    pub timer_noticed: [bool; 4],
+
     // The address the player's spells will act on.
     pub selected: u8,
 
+    // A byte held by the copier, outside of RAM.
+    pub accumulator: u8,
+
+    // What brought HP to 0, for the morgue file.
+    pub death_cause: Option<String>,
+
+    // Deepest level reached by stairs, for scoring; falls and zips through
+    // the edge of the world don't count.
+    pub deepest: u8,
+
+    // Set when the player makes it out of the cellar with the pendant.
+    pub escaped: bool,
+
+    // Turns of palantir sight left, counted down by timer::tick. Not in RAM.
+    pub scrying: u8,
+
+    // Turns the player loses before they can act again; counted down at
+    // the end of each lost turn. Not in RAM.
+    pub paralysis: u8,
+
     pub stairs_delta: u8,
     pub timer_delta: u8,
     pub damage_offset: i8,
@@ -43,26 +92,36 @@ pub struct Player {
     // Interface
     pub show_ram: bool,
 
+    // Recalculated by World::update_visibility after every command.
     pub visible: HashSet<Point>
 }
 
 impl Player {
     // enters first level automatically
-    pub fn new(log: &mut Log, dungeon: &mut Dungeon) -> Player {
+    pub fn new(log: &mut Log, dungeon: &mut Dungeon, appearance_map: &AppearanceMap) -> Player {
         let mut player = Player {
             position: Point(-1, -1),
             depth: 1,
-            // TODO naming the player
-            name: [97, 98, 99, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            // filled in by main before the game starts
+            name: [0; 15],
             hp: 10,
             tp: 3,
             xl: 1,
             def: 0,
             aptitude: [0, 0, 0, 0],
+            equipment_bonus: (0, [0, 0, 0, 0]),
             inventory: Inventory::empty(),
             spell_memory: [false; 8],
+            identification: !item::SHUFFLED_APPEARANCES,
             timer: [0; 4],
+            timer_noticed: [false; 4],
             selected: 0x00,
+            accumulator: 0x00,
+            death_cause: None,
+            deepest: 1,
+            escaped: false,
+            scrying: 0,
+            paralysis: 0,
             stairs_delta: 1,
             timer_delta: 0xFF,
             damage_offset: 0,
@@ -71,7 +130,7 @@ impl Player {
             visible: HashSet::new()
         };
         log.tell(speech::intro_line());
-        player.enter_level(log, dungeon, 1, Stairs::Up);
+        player.enter_level(log, dungeon, appearance_map, 1, Stairs::Up);
         player
     }
 
@@ -83,14 +142,92 @@ impl Player {
         &mut dungeon[self.depth as usize - 1]
     }
 
+    pub fn timer_active(&self, timer: Timer) -> bool {
+        self.timer[timer as usize] != 0
+    }
+
+    // damage dealt by the player is doubled while charged
+    pub fn charge_damage(&self, damage: i32) -> i32 {
+        if self.timer_active(Timer::Charge) { damage * 2 } else { damage }
+    }
+
+    // damage taken by the player is halved (rounding down) while protected
+    pub fn protect_damage(&self, damage: i32) -> i32 {
+        if self.timer_active(Timer::Protect) { damage / 2 } else { damage }
+    }
+
+    // lose HP, remembering the cause in case it's fatal
+    pub fn hurt(&mut self, damage: i32, cause: String) {
+        if damage <= 0 {
+            return
+        }
+        let was_alive = self.hp > 0;
+        self.hp = self.hp.saturating_sub(min(damage, 0xff) as u8);
+        if was_alive && self.hp == 0 {
+            self.death_cause = Some(cause);
+        }
+    }
+
+    // lower XL, bringing HP and TP down to the new caps
+    pub fn lose_levels(&mut self, levels: u8) {
+        self.xl = self.xl.saturating_sub(levels);
+        self.hp = min(self.hp, self.max_hp());
+        self.tp = min(self.tp, self.max_tp());
+    }
+
+    // HP and TP are capped by XL
+    pub fn max_hp(&self) -> u8 {
+        self.xl.saturating_mul(10)
+    }
+
+    pub fn max_tp(&self) -> u8 {
+        self.xl.saturating_mul(3)
+    }
+
+    // None while the game is still going
+    pub fn ending(&self) -> Option<Ending> {
+        if self.escaped {
+            Some(Ending::Escape)
+        } else if self.xl == 0 {
+            Some(Ending::Death(String::from("drained to death")))
+        } else if self.hp == 0 {
+            Some(Ending::Death(self.death_cause.clone()
+                .unwrap_or_else(|| String::from("zeroed out"))))
+        } else {
+            None
+        }
+    }
+
+    pub fn score(&self) -> u32 {
+        let mut score = self.deepest as u32 * 100 + self.xl as u32 * 10;
+        if self.inventory.contains(Appearance::GoldenPendant) {
+            score += 1000;
+        }
+        if self.escaped {
+            score += 5000;
+        }
+        score
+    }
+
     // enter a level: put the player on the appropriate stairs.
-    fn enter_level(&mut self, log: &mut Log, dungeon: &mut Dungeon, depth: u8, entry: Stairs) {
+    fn enter_level(&mut self, log: &mut Log, dungeon: &mut Dungeon,
+        appearance_map: &AppearanceMap, depth: u8, entry: Stairs)
+    {
         if depth == 0 {
-            // TODO: special case for level 0
+            // only climbing the up stairs out of level 1 leads outside
+            let climbing_out = self.depth == 1 && entry == Stairs::Down;
+            if climbing_out && self.inventory.contains(Appearance::GoldenPendant) {
+                log.tell("You climb out of the cellar into the daylight, pendant in hand!");
+                self.escaped = true;
+            } else {
+                // the world takes care of this; see World::settle
+                self.depth = 0;
+            }
         } else {
             self.depth = depth;
+            self.deepest = max(self.deepest, depth);
 
-            let mut level = self.current_level_mut(dungeon);
+            let level = self.current_level(dungeon);
 
             for tile_position in grid::RECTANGLE {
                 if level.tiles[tile_position] == Tile::Stairs(entry) {
@@ -99,35 +236,36 @@ impl Player {
                 }
             }
 
-            self.update_visibility(&mut level);
-            self.look_at_floor(log, level)
+            self.look_at_floor(log, level, appearance_map)
         }
     }
 
-    pub fn try_stairs(&mut self, log: &mut Log, dungeon: &mut Dungeon, stairs: Stairs) {
+    pub fn try_stairs(&mut self, log: &mut Log, dungeon: &mut Dungeon,
+        appearance_map: &AppearanceMap, stairs: Stairs)
+    {
         if self.current_level(dungeon).tiles[self.position] == Tile::Stairs(stairs) {
             match stairs {
                 Stairs::Up   => log.tell("You go up the stairs."),
                 Stairs::Down => log.tell("You go down the stairs.")
             };
             let destination = stairs.destination(self);
-            self.enter_level(log, dungeon, destination, stairs.flip())
+            self.enter_level(log, dungeon, appearance_map, destination, stairs.flip())
         }
     }
 
     // try to walk in given direction.
     // returns whether this consumes a turn
-    pub fn step(&mut self, log: &mut Log, dungeon: &mut Dungeon, direction: Point) -> bool {
-        let mut level = self.current_level_mut(dungeon);
+    pub fn step(&mut self, log: &mut Log, dungeon: &mut Dungeon,
+        appearance_map: &AppearanceMap, direction: Point) -> bool
+    {
+        let level = self.current_level_mut(dungeon);
         let new_position = self.position + direction;
 
         // don't let the player step out of bounds
         if !grid::RECTANGLE.contains(new_position) { return false }
 
         if let Some(monster) = level.monster_at_mut(new_position) {
-            // attack
-            log.tell(format!("You hit the {}.", monster.name()));
-            monster.hp = 0;
+            self.attack(log, monster);
             return true
         }
 
@@ -135,14 +273,12 @@ impl Player {
             Tile::Wall => false,
             Tile::Floor | Tile::Doorway | Tile::Stairs(_) => {
                 self.position = new_position;
-                self.update_visibility(&mut level);
-                self.look_at_floor(log, level);
+                self.look_at_floor(log, level, appearance_map);
                 true
             },
             Tile::Door => {
                 level.tiles[new_position] = Tile::Doorway;
                 log.tell("You open the door.");
-                self.update_visibility(&mut level);
                 true
             },
             Tile::Switch(bn) => {
@@ -153,14 +289,102 @@ impl Player {
         }
     }
 
-    fn update_visibility(&mut self, level: &mut Level) {
-        self.visible = fov::calculate(level, self.position);
-        level.known_tiles.extend(&self.visible)
+    // roll damage for a melee attack: XLd(base + aptitude) with the equipped
+    // weapon, or 2dXL unarmed, plus the damage offset
+    fn melee_damage(&self) -> i32 {
+        let xl = self.xl as i32;
+        let weapon = self.inventory.equipped(EquipmentSlot::Weapon)
+            .and_then(|item| item.melee_damage());
+        let roll = match weapon {
+            Some((base, element)) => roll(xl, base + self.aptitude[element as usize] as i32),
+            None => roll(2, xl)
+        };
+        self.charge_damage(roll + self.damage_offset as i32)
+    }
+
+    fn attack(&self, log: &mut Log, monster: &mut Monster) {
+        self.strike(log, monster, self.melee_damage());
+    }
+
+    // deal damage to a monster, minus its defense
+    fn strike(&self, log: &mut Log, monster: &mut Monster, damage: i32) {
+        let damage = damage - monster.defense() as i32;
+
+        if damage <= 0 {
+            log.tell(format!("You miss the {}.", monster.name()));
+            return
+        }
+
+        monster.hp = monster.hp.saturating_sub(min(damage, 0xff) as u8);
+        if monster.alive() {
+            log.tell(format!("You hit the {} for {} damage.", monster.name(), damage));
+        } else {
+            log.tell(format!("You kill the {}! ({} damage)", monster.name(), damage));
+        }
+    }
+
+    // the wielded weapon's (base, element), if it can be fired
+    pub fn ranged_weapon(&self) -> Option<(i32, Element)> {
+        self.inventory.equipped(EquipmentSlot::Weapon)
+            .and_then(|item| item.ranged_damage())
+    }
+
+    // The path a shot at `target` would take: it stops short of anything
+    // that isn't open, or at the first monster in the way.
+    pub fn line_of_fire(&self, dungeon: &Dungeon, target: Point) -> Vec<Point> {
+        let level = self.current_level(dungeon);
+        let mut path = vec![];
+        for position in self.position.line_to(target) {
+            if !grid::RECTANGLE.contains(position) || !level.tiles[position].is_open() {
+                break
+            }
+            path.push(position);
+            if level.monster_at(position).is_some() {
+                break
+            }
+        }
+        path
+    }
+
+    // fire the wielded weapon along a path from line_of_fire; monsters of
+    // the weapon's element take half damage. Returns if a turn was consumed.
+    pub fn fire(&self, log: &mut Log, dungeon: &mut Dungeon, path: &[Point]) -> bool {
+        let (base, element) = match self.ranged_weapon() {
+            Some(weapon) => weapon,
+            None => {
+                log.tell("You aren't wielding anything you can fire.");
+                return false
+            }
+        };
+        let end = match path.last() {
+            Some(&end) => end,
+            None => {
+                log.tell("You can't fire there.");
+                return false
+            }
+        };
+
+        log.tell("You fire a glob of jelly.");
+        let level = self.current_level_mut(dungeon);
+        match level.monster_at_mut(end) {
+            Some(monster) => {
+                let mut damage = roll(self.xl as i32, base + self.aptitude[element as usize] as i32)
+                    + self.damage_offset as i32;
+                if monster.element() == Some(element) {
+                    log.tell(format!("The {} partly absorbs it.", monster.name()));
+                    damage /= 2;
+                }
+                self.strike(log, monster, self.charge_damage(damage));
+            },
+            None => log.tell("It splats on the floor.")
+        }
+        true
     }
 
-    fn look_at_floor(&self, log: &mut Log, level: &Level) {
+    fn look_at_floor(&self, log: &mut Log, level: &Level, appearance_map: &AppearanceMap) {
         if let Some(item) = level.items.get(&self.position) {
-            log.tell(format!("You see here {}.", a_or_an(item.name())));
+            log.tell(format!("You see here {}.",
+                a_or_an(item.name(self.identification, appearance_map))));
         }
 
         if let Tile::Stairs(stairs) = level.tiles[self.position] {
@@ -180,7 +404,9 @@ impl Player {
 
 
     // try to pick up an item from the floor; returns if a turn was consumed
-    pub fn pick_up_item(&mut self, log: &mut Log, dungeon: &mut Dungeon) -> bool {
+    pub fn pick_up_item(&mut self, log: &mut Log, dungeon: &mut Dungeon,
+        appearance_map: &AppearanceMap) -> bool
+    {
         use std::collections::hash_map::Entry::*;
 
         let mut level = self.current_level_mut(dungeon);
@@ -189,7 +415,8 @@ impl Player {
             let item = *floor_item.get();
             if self.inventory.insert(item) {
                 floor_item.remove();
-                log.tell(format!("You pick up the {}.", item.name()));
+                log.tell(format!("You pick up the {}.",
+                    item.name(self.identification, appearance_map)));
                 true
             } else {
                 log.tell("Your inventory is full!");
@@ -202,19 +429,19 @@ impl Player {
     }
 
     // try to drop an item to the floor; returns if a turn was consumed
-    pub fn drop_item(&mut self, log: &mut Log,
-        dungeon: &mut Dungeon, index: BitNumber) -> bool
+    pub fn drop_item(&mut self, log: &mut Log, dungeon: &mut Dungeon,
+        appearance_map: &AppearanceMap, index: BitNumber) -> bool
     {
         use rand::Rng;
         use rand::thread_rng;
         use std::collections::hash_map::Entry::*;
 
-        // TODO: special handling for equipped items?
         let slot = self.inventory.slots[index as usize];
         let item = match slot.get_item() {
             Some(item) => {
                 if slot.is_cursed() {
-                    log.tell(format!("You can't drop the cursed {}!", item.name()));
+                    log.tell(format!("You can't drop the cursed {}!",
+                        item.name(self.identification, appearance_map)));
                     return false
                 } else {
                     item
@@ -244,12 +471,18 @@ impl Player {
         for &direction in directions.iter() {
             let position = self.position + direction;
 
+            if !grid::RECTANGLE.contains(position) { continue }
+
             let tile = level.tiles[position];
 
             if tile == Tile::Floor || tile == Tile::Doorway {
                 if let Vacant(floor) = level.items.entry(position) {
+                    if slot.is_equipped() {
+                        self.unequip(log, appearance_map, index as usize);
+                    }
                     self.inventory.slots[index as usize] = InventorySlot::empty();
-                    log.tell(format!("You drop the {}.", item.name()));
+                    log.tell(format!("You drop the {}.",
+                        item.name(self.identification, appearance_map)));
                     floor.insert(item);
                     return true
                 }
@@ -262,8 +495,8 @@ impl Player {
     }
 
     // try to use an item in inventory; returns if a turn was consumed
-    pub fn use_item(&mut self, log: &mut Log,
-        dungeon: &mut Dungeon, index: BitNumber) -> bool
+    pub fn use_item(&mut self, log: &mut Log, dungeon: &mut Dungeon,
+        appearance_map: &AppearanceMap, index: BitNumber) -> bool
     {
         let slot = self.inventory.slots[index as usize];
         let item = match slot.get_item() {
@@ -275,17 +508,343 @@ impl Player {
         };
 
         if item.is_consumable() {
-            log.tell("[Item consumed. Do something here.]");
             self.inventory.slots[index as usize] = InventorySlot::empty();
+
+            let kind = item.kind(appearance_map);
+            let obvious = match kind {
+                Kind::FullHPFruit | Kind::FullTPFruit | Kind::CancellationFruit => {
+                    log.tell(format!("You eat the {}.",
+                        item.name(self.identification, appearance_map)));
+                    self.eat_fruit(log, dungeon, kind)
+                },
+                _ => {
+                    log.tell(format!("You swallow the {}.",
+                        item.name(self.identification, appearance_map)));
+                    self.take_pill(log, dungeon, kind, item.is_enchanted(), item.is_cursed());
+                    true
+                }
+            };
+
+            if obvious {
+                self.identify(log, appearance_map, item);
+            }
             return true
         }
 
-        if let Some(body_part) = item.equipment_slot() {
-            log.tell(format!("[Item is {:?} equipment. Try to equip/unequip.]", body_part));
-            return false
+        match item.kind(appearance_map) {
+            Kind::Manual => return self.read_book(log, appearance_map, index, item, false),
+            Kind::Guidebook => return self.read_book(log, appearance_map, index, item, true),
+            Kind::Palantir => {
+                self.inventory.slots[index as usize] = InventorySlot::empty();
+                self.scrying = SCRYING_DURATION;
+                log.tell("You gaze into the palantir, and the whole level unfolds before you!");
+                log.tell("The palantir clouds over and shatters.");
+                return true
+            },
+            _ => {}
+        }
+
+        if let Some(equipment_slot) = item.equipment_slot() {
+            return if slot.is_equipped() {
+                self.unequip(log, appearance_map, index as usize)
+            } else {
+                self.equip(log, appearance_map, index as usize, equipment_slot)
+            }
         }
 
         log.tell("[Not a consumable or equipment. Do something here.]");
         false
     }
+
+    // zap a wand of death, using it up: a ray kills the first monster in
+    // line, stopping at anything that isn't open. Returns if a turn was consumed.
+    pub fn zap_wand(&mut self, log: &mut Log, dungeon: &mut Dungeon,
+        index: BitNumber, direction: Point) -> bool
+    {
+        if direction == Point(0, 0) {
+            log.tell("You decide not to zap yourself.");
+            return false
+        }
+
+        self.inventory.slots[index as usize] = InventorySlot::empty();
+        log.tell("You zap the wand of death, and it crumbles to dust.");
+
+        let level = self.current_level_mut(dungeon);
+        let mut position = self.position + direction;
+        while grid::RECTANGLE.contains(position) && level.tiles[position].is_open() {
+            if let Some(monster) = level.monster_at_mut(position) {
+                if monster.kind == monster::Kind::GoldenDragon {
+                    log.tell("The golden dragon shrugs off the deathly ray!");
+                } else {
+                    monster.hp = 0;
+                    log.tell(format!("The {} dies instantly!", monster.name()));
+                }
+                return true
+            }
+            position = position + direction;
+        }
+
+        log.tell("The ray fizzles out.");
+        true
+    }
+
+    // learn an unknown spell from a book, using it up; guidebooks teach the
+    // expensive spells first. Returns if a turn was consumed.
+    fn read_book(&mut self, log: &mut Log, appearance_map: &AppearanceMap,
+        index: BitNumber, item: Item, better: bool) -> bool
+    {
+        let unknown: Vec<&Spell> = spell::SPELLS.iter()
+            .filter(|spell| !self.spell_memory[spell.bit() as usize])
+            .collect();
+
+        if unknown.is_empty() {
+            log.tell(format!("You leaf through the {}, but you already know everything in it.",
+                item.name(self.identification, appearance_map)));
+            return false
+        }
+
+        let better_unknown: Vec<&Spell> = unknown.iter()
+            .filter(|spell| spell.cost() > 1)
+            .cloned()
+            .collect();
+
+        let spell = if better && !better_unknown.is_empty() {
+            pick(better_unknown)
+        } else {
+            pick(unknown)
+        };
+
+        self.spell_memory[spell.bit() as usize] = true;
+        self.inventory.slots[index as usize] = InventorySlot::empty();
+        log.tell(format!("You read the {} and learn {}! It crumbles to dust.",
+            item.name(self.identification, appearance_map), spell.name()));
+        true
+    }
+
+    // apply a fruit's effect; returns whether it was obvious what happened
+    fn eat_fruit(&mut self, log: &mut Log, dungeon: &mut Dungeon, kind: Kind) -> bool {
+        match kind {
+            Kind::FullHPFruit => if self.hp < self.max_hp() {
+                self.hp = self.max_hp();
+                log.tell("You feel completely healed!");
+                true
+            } else {
+                log.tell("It's very filling.");
+                false
+            },
+
+            Kind::FullTPFruit => if self.tp < self.max_tp() {
+                self.tp = self.max_tp();
+                log.tell("Your mind feels sharp and clear!");
+                true
+            } else {
+                log.tell("It's very refreshing.");
+                false
+            },
+
+            Kind::CancellationFruit => {
+                let mut cancelled = self.timer.iter().any(|&t| t != 0);
+                self.timer = [0; 4];
+
+                for monster in self.current_level_mut(dungeon).monsters.iter_mut() {
+                    if monster.alive() && (monster.charged || monster.corrupted) {
+                        cancelled = true;
+                    }
+                    monster.charged = false;
+                    monster.corrupted = false;
+                }
+
+                if cancelled {
+                    log.tell("A wave of stillness washes over the level.");
+                } else {
+                    log.tell("It tastes bland.");
+                }
+                cancelled
+            },
+
+            _ => false
+        }
+    }
+
+    // apply a pill's effect: enchanted pills are stronger, cursed pills backfire
+    fn take_pill(&mut self, log: &mut Log, dungeon: &mut Dungeon,
+        kind: Kind, enchanted: bool, cursed: bool)
+    {
+        let duration = if enchanted {PILL_DURATION * 2} else {PILL_DURATION};
+        let levels = if enchanted {4} else {2};
+
+        match kind {
+            Kind::ChargePill => self.dose_timer(log, Timer::Charge, duration, cursed),
+            Kind::HastePill => self.dose_timer(log, Timer::Haste, duration, cursed),
+            Kind::ProtectPill => self.dose_timer(log, Timer::Protect, duration, cursed),
+            Kind::PoisonPill => self.dose_timer(log, Timer::Poison, duration, cursed),
+
+            Kind::XPUpPill | Kind::XPDownPill => {
+                if (kind == Kind::XPUpPill) != cursed {
+                    self.xl = min(self.xl + levels, MAX_XL);
+                    log.tell("You feel more experienced!");
+                } else {
+                    self.lose_levels(levels);
+                    log.tell("You feel less experienced...");
+                }
+            },
+
+            Kind::IdentifyPill => if cursed {
+                self.identification &= !item::SHUFFLED_APPEARANCES;
+                log.tell("Your memory clouds over...");
+            } else {
+                let unknown: Vec<Item> = self.inventory.slots.iter()
+                    .filter_map(|slot| slot.get_item())
+                    .filter(|item| self.identification & item.appearance().identification_mask() == 0)
+                    .collect();
+
+                if unknown.is_empty() {
+                    log.tell("You feel knowledgeable, but you already know what you carry.");
+                } else if enchanted {
+                    for item in unknown {
+                        self.identification |= item.appearance().identification_mask();
+                    }
+                    log.tell("You understand everything you carry!");
+                } else {
+                    self.identification |= pick(unknown).appearance().identification_mask();
+                    log.tell("Something in your pack suddenly makes sense.");
+                }
+            },
+
+            Kind::TormentPill => {
+                let spare_monsters = cursed;
+                let spare_player = enchanted && !cursed;
+
+                if !spare_monsters {
+                    for monster in self.current_level_mut(dungeon).monsters.iter_mut() {
+                        if monster.alive() {
+                            monster.hp -= monster.hp / 2;
+                        }
+                    }
+                    log.tell("A shriek of agony echoes through the cellar!");
+                }
+                if !spare_player {
+                    self.hp -= self.hp / 2;
+                    log.tell("You are wracked with pain!");
+                }
+            },
+
+            _ => {}
+        }
+    }
+
+    // start a timer (or stop it, if cursed); tick announces the change
+    fn dose_timer(&mut self, log: &mut Log, timer: Timer, duration: u8, cursed: bool) {
+        let value = &mut self.timer[timer as usize];
+        if cursed {
+            if *value == 0 {
+                log.tell("You feel a strange absence.");
+            }
+            *value = 0;
+        } else {
+            *value = max(*value, duration);
+        }
+    }
+
+    // learn the true kind of an item's appearance, announcing it if it's news
+    pub fn identify(&mut self, log: &mut Log, appearance_map: &AppearanceMap, item: Item) {
+        let mask = item.appearance().identification_mask();
+        if self.identification & mask == 0 {
+            self.identification |= mask;
+            log.tell(format!("That was {}.", a_or_an(item.kind(appearance_map).name())));
+        }
+    }
+
+    // Worn armor and necklaces add their modifiers to def and aptitude, and
+    // so to RAM, saturating at -8 and 7 like the RAM nibbles. What was
+    // actually added is kept in equipment_bonus, so taking an item off gives
+    // back exactly the base it was put on over, pokes to def or aptitude in
+    // between shift that base, and equip bits poked in RAM are picked up the
+    // next time this runs (see World::settle).
+    pub fn update_equipment_bonus(&mut self) {
+        let mut target = (0, [0; 4]);
+        for &slot in [EquipmentSlot::Body, EquipmentSlot::Neck].iter() {
+            if let Some(item) = self.inventory.equipped(slot) {
+                let (def, aptitude) = item.modifiers();
+                target.0 += def;
+                for (value, delta) in target.1.iter_mut().zip(aptitude.iter()) {
+                    *value += delta;
+                }
+            }
+        }
+
+        let (ref mut def_bonus, ref mut aptitude_bonus) = self.equipment_bonus;
+        apply_bonus(&mut self.def, def_bonus, target.0);
+        for ((value, bonus), &modifier) in self.aptitude.iter_mut()
+            .zip(aptitude_bonus.iter_mut()).zip(target.1.iter())
+        {
+            apply_bonus(value, bonus, modifier);
+        }
+    }
+
+    // lose an item to some outside force, ignoring curses
+    pub fn lose_item(&mut self, index: usize) -> Option<Item> {
+        let slot = self.inventory.slots[index];
+        let item = slot.get_item();
+        if item.is_some() {
+            self.inventory.slots[index] = InventorySlot::empty();
+            self.update_equipment_bonus();
+        }
+        item
+    }
+
+    // equip an item, first unequipping whatever is in the same equipment
+    // slot; returns if a turn was consumed
+    fn equip(&mut self, log: &mut Log, appearance_map: &AppearanceMap,
+        index: usize, equipment_slot: EquipmentSlot) -> bool
+    {
+        while let Some(other) = self.inventory.equipped_index(equipment_slot) {
+            if !self.unequip(log, appearance_map, other) {
+                return false
+            }
+        }
+
+        let slot = self.inventory.slots[index];
+        self.inventory.slots[index] = slot.equip();
+        self.update_equipment_bonus();
+
+        if let Some(item) = slot.get_item() {
+            let (verb, _) = equipment_slot.verbs();
+            log.tell(format!("You {} the {}.", verb,
+                item.name(self.identification, appearance_map)));
+        }
+        true
+    }
+
+    // try to unequip an item; cursed items refuse to come off.
+    // returns if a turn was consumed
+    fn unequip(&mut self, log: &mut Log, appearance_map: &AppearanceMap, index: usize) -> bool {
+        let slot = self.inventory.slots[index];
+        let item = match slot.get_item() {
+            Some(item) => item,
+            None => return false
+        };
+        let name = item.name(self.identification, appearance_map);
+
+        if slot.is_cursed() {
+            log.tell(format!("You can't remove the cursed {}!", name));
+            return false
+        }
+
+        self.inventory.slots[index] = slot.unequip();
+        self.update_equipment_bonus();
+        if let Some(equipment_slot) = item.equipment_slot() {
+            let (_, verb) = equipment_slot.verbs();
+            log.tell(format!("You {} the {}.", verb, name));
+        }
+        true
+    }
+}
+
+// swap the bonus already added to a stat for a new one, saturating at -8..7,
+// and remember how much was actually added
+fn apply_bonus(value: &mut i8, bonus: &mut i8, modifier: i8) {
+    let base = *value - *bonus;
+    *value = (base + modifier).clamp(-8, 7);
+    *bonus = *value - base;
 }
@@ -1,56 +1,130 @@
 use byte::BitNumber;
 use byte::BitNumber::*;
+use memory;
+use util;
+use world::World;
 
 pub struct Spell {
     name: &'static str,
     description: &'static str,
     bit: BitNumber,
+    cost: u8,
 }
 
 pub const ONE: Spell = Spell {
     name: "ONE",
     description: "Writes 0x01 to the selected address.",
     bit: Bit0,
+    cost: 1,
 };
 
 pub const CLO: Spell = Spell {
     name: "CLO",
     description: "Clear the leftmost 1 bit of the target value.",
     bit: Bit1,
+    cost: 1,
 };
 
 pub const INC: Spell = Spell {
     name: "INC",
     description: "Increment the target value, wrapping from 0xFF to 0x00 on overflow.",
     bit: Bit2,
+    cost: 1,
 };
 
 pub const CPN: Spell = Spell {
     name: "CPN",
     description: "Set the target value to that of the byte after it in memory, cycling from 0x3F back to 0x00.",
     bit: Bit3,
+    cost: 2,
 };
 
 pub const A9D: Spell = Spell {
     name: "A9D",
     description: "Add 0x9D to the target value, wrapping on overflow. In decimal, this is 157 (unsigned) or -99 (signed).",
     bit: Bit4,
+    cost: 1,
 };
 
 pub const REV: Spell = Spell {
     name: "REV",
     description: "Reverses the bits of the target value.",
     bit: Bit5,
+    cost: 1,
 };
 
 pub const WLN: Spell = Spell {
     name: "WLN",
     description: "Rewrite the lower nibble of the target value freely.",
     bit: Bit6,
+    cost: 2,
 };
 
 pub const WHN: Spell = Spell {
     name: "WHN",
     description: "Rewrite the higher nibble of the target value freely.",
     bit: Bit7,
+    cost: 2,
 };
+
+// Index with byte::BitNumber, like Player::spell_memory.
+pub const SPELLS: [Spell; 8] = [ONE, CLO, INC, CPN, A9D, REV, WLN, WHN];
+
+impl Spell {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+
+    pub fn bit(&self) -> BitNumber {
+        self.bit
+    }
+
+    // TP spent on each cast.
+    pub fn cost(&self) -> u8 {
+        self.cost
+    }
+
+    // WLN and WHN ask the caster which nibble to write.
+    pub fn needs_nibble(&self) -> bool {
+        self.bit == Bit6 || self.bit == Bit7
+    }
+
+    // compute the new value of a byte, given the byte after it in memory
+    // (for CPN) and a chosen nibble (for WLN and WHN)
+    pub fn apply(&self, value: u8, next: u8, nibble: u8) -> u8 {
+        match self.bit {
+            Bit0 => 0x01,
+            Bit1 => if value == 0 { 0 } else { value & !(0x80 >> value.leading_zeros()) },
+            Bit2 => value.wrapping_add(1),
+            Bit3 => next,
+            Bit4 => value.wrapping_add(0x9d),
+            Bit5 => value.reverse_bits(),
+            Bit6 => (value & 0xf0) | (nibble & 0x0f),
+            Bit7 => (nibble << 4) | (value & 0x0f),
+        }
+    }
+}
+
+// spend TP and apply a spell to the player's selected address.
+// returns whether the spell was cast
+pub fn cast(world: &mut World, spell: &Spell, nibble: u8) -> bool {
+    if world.player.tp < spell.cost {
+        world.log.tell(format!("You don't have enough TP to cast {}.", spell.name));
+        return false
+    }
+    world.player.tp -= spell.cost;
+
+    let address = world.player.selected;
+    let value = memory::peek(world, address);
+    let next = memory::peek(world, (address + 1) % 0x40);
+    let result = spell.apply(value, next, nibble);
+
+    world.log.tell(format!("You cast {} on the {}. ({:02x} -> {:02x})",
+        spell.name, util::address_name(address), value, result));
+    memory::poke(world, address, result);
+    true
+}
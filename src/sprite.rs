@@ -37,6 +37,11 @@ pub const HIDDEN: Sprite = Sprite {
     color: DARK
 };
 
+pub const JELLY_GLOB: Sprite = Sprite {
+    character: '*',
+    color: LIME
+};
+
 impl Sprite {
     pub fn of_byte(appearance: u8, bright: bool) -> Self {
         Sprite {
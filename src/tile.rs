@@ -21,7 +21,7 @@ pub enum Stairs {
 impl Tile {
     pub fn sprite(self, world: &World) -> Sprite {
         match self {
-            Tile::Floor =>                Sprite {character: '.',  color: GRAY},
+            Tile::Floor =>                Sprite::of_byte(world.floor_appearance_byte, false),
             Tile::Wall =>                 Sprite::of_byte(world.wall_appearance_byte, false),
             Tile::Door =>                 Sprite::of_byte(world.door_appearance_byte, false),
             Tile::Doorway =>              Sprite {character: '\'', color: BROWN},
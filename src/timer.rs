@@ -1,3 +1,5 @@
+use world::World;
+
 #[derive(Copy, Clone)]
 pub enum Timer {
     Poison = 0x00,
@@ -5,3 +7,75 @@ pub enum Timer {
     Charge = 0x02,
     Protect = 0x03,
 }
+
+pub const TIMERS: [Timer; 4] = [Timer::Poison, Timer::Haste, Timer::Charge, Timer::Protect];
+
+impl Timer {
+    pub fn name(self) -> &'static str {
+        match self {
+            Timer::Poison => "poison",
+            Timer::Haste => "haste",
+            Timer::Charge => "charge",
+            Timer::Protect => "protect",
+        }
+    }
+
+    fn start_message(self) -> &'static str {
+        match self {
+            Timer::Poison => "You feel very sick.",
+            Timer::Haste => "You feel yourself speed up.",
+            Timer::Charge => "Power crackles through your limbs.",
+            Timer::Protect => "A protective aura surrounds you.",
+        }
+    }
+
+    fn end_message(self) -> &'static str {
+        match self {
+            Timer::Poison => "You feel less sick.",
+            Timer::Haste => "You feel yourself slow down.",
+            Timer::Charge => "The crackling power fades.",
+            Timer::Protect => "Your protective aura fades away.",
+        }
+    }
+}
+
+// the end-of-turn timer pass: apply the effect of each running timer, then
+// add the timer delta to it. the delta is normally 0xff, so timers count
+// down, but a poked delta can just as well make them grow
+pub fn tick(world: &mut World) {
+    for &timer in TIMERS.iter() {
+        let index = timer as usize;
+
+        // timers can be started by anything that writes to RAM, so we find
+        // out about new ones here rather than where they're set
+        if world.player.timer[index] != 0 && !world.player.timer_noticed[index] {
+            world.log.tell(timer.start_message());
+            world.player.timer_noticed[index] = true;
+        }
+
+        if world.player.timer[index] != 0 {
+            if let Timer::Poison = timer {
+                world.player.hurt(1, String::from("poisoned to death"));
+            }
+            world.player.timer[index] =
+                world.player.timer[index].wrapping_add(world.player.timer_delta);
+        }
+
+        if world.player.timer[index] == 0 && world.player.timer_noticed[index] {
+            world.log.tell(timer.end_message());
+            world.player.timer_noticed[index] = false;
+        }
+    }
+
+    if world.player.scrying > 0 {
+        world.player.scrying -= 1;
+        if world.player.scrying == 0 {
+            world.log.tell("The palantir's visions fade.");
+        }
+    }
+}
+
+// while hasted, the player gets a free move every other turn
+pub fn monsters_skip_turn(world: &World) -> bool {
+    world.player.timer_active(Timer::Haste) && world.log.turn() % 2 == 1
+}
@@ -48,6 +48,33 @@ pub fn random_range_two<T: PartialOrd + SampleRange>(r: Range<T>) -> (T, T)
     (a, b)
 }
 
+// Roll `count` dice with `sides` sides each; dice with no sides roll 0.
+pub fn roll(count: i32, sides: i32) -> i32 {
+    if sides <= 0 {
+        return 0
+    }
+    (0..count).map(|_| random_range(1..sides + 1)).sum()
+}
+
+// Break text into lines of at most `width` characters at spaces.
+pub fn word_wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for word in text.split_whitespace() {
+        let fits = match lines.last() {
+            Some(line) => line.len() + 1 + word.len() <= width,
+            None => false
+        };
+        if fits {
+            let line = lines.last_mut().unwrap();
+            line.push(' ');
+            line.push_str(word);
+        } else {
+            lines.push(String::from(word));
+        }
+    }
+    lines
+}
+
 pub fn address_name(address: u8) -> &'static str {
     match address {
         0x00 => "player appearance",
@@ -100,13 +127,13 @@ pub fn address_name(address: u8) -> &'static str {
         0x2f => "inventory[7]",
         0x30 => "door appearance",
         0x31 => "wall appearance",
-        0x32 => "floor color",
+        0x32 => "floor appearance",
         0x33 => "stairs delta",
         0x34 => "timer delta",
         0x35 => "damage offset",
-        0x36 => "0x36",
-        0x37 => "0x37",
-        0x38 => "0x38",
+        0x36 => "light radius",
+        0x37 => "instability",
+        0x38 => "text sync",
         0x39 => "player hp",
         0x3a => "player tp",
         0x3b => "player xl/def",
@@ -3,13 +3,16 @@ use pancurses;
 use pancurses::{Window, Attribute, Attributes, ColorPair, ToChtype};
 use memory;
 use sprite;
+use spell;
 use sprite::{Sprite, Color};
 use geometry::*;
 use util::{self, pick};
 use world::World;
-use log::Log;
-use item::Inventory;
+use morgue;
+use player::Ending;
 use tile::Tile;
+use timer;
+use rand::{Rng, SeedableRng, XorShiftRng};
 
 
 fn color(color: Color) -> Attributes {
@@ -58,7 +61,60 @@ pub fn initialize() -> Window {
 
 // TODO: write an actual dang view
 
+pub fn draw_name_entry(term: &Window, name: &str, error: Option<String>) {
+    // TODO: depend on terminal size; don't hardcode lengths/alignments
+    term.erase();
+
+    term.attrset(color(Color::White));
+    term.mvaddstr(4, 30, "Who goes down into the cellar?");
+
+    term.attrset(color(Color::Gray));
+    term.mvaddstr(6, 30, &format!("> {}_", name));
+
+    if let Some(error) = error {
+        term.attrset(color(Color::Red));
+        term.mvaddstr(8, 30, &error);
+    }
+
+    term.attrset(color(Color::Dark));
+    term.mvaddstr(10, 30, &format!("(up to {} characters; enter to begin)",
+        memory::MAX_NAME_LENGTH));
+
+    term.refresh();
+}
+
 pub fn draw(term: &Window, world: &World) {
+    draw_frame(term, world);
+    term.refresh();
+}
+
+// draw the board with a targeting cursor and the path a shot would take
+pub fn draw_target(term: &Window, world: &World, target: Point, path: &[Point]) {
+    draw_frame(term, world);
+
+    let reverse = pancurses::chtype::from(Attribute::Reverse);
+    for &position in path {
+        let Point(col, row) = position;
+        term.mvaddch(row + 4, col + 30, board_cell(world, position) | reverse);
+    }
+
+    let Point(col, row) = target;
+    term.mvaddch(row + 4, col + 30, cell(Sprite {character: 'X', color: sprite::YELLOW}) | reverse);
+
+    term.refresh();
+}
+
+// animate a projectile flying along a path
+pub fn draw_projectile(term: &Window, world: &World, path: &[Point], projectile: Sprite) {
+    for &Point(col, row) in path {
+        draw_frame(term, world);
+        term.mvaddch(row + 4, col + 30, cell(projectile));
+        term.refresh();
+        pancurses::napms(40);
+    }
+}
+
+fn draw_frame(term: &Window, world: &World) {
     term.erase(); // clear back-buffer
 
     if world.player.show_ram {
@@ -68,9 +124,7 @@ pub fn draw(term: &Window, world: &World) {
     }
 
     draw_board(term, &world);
-    draw_messages(term, &world.log);
-
-    term.refresh();
+    draw_messages(term, world);
 }
 
 fn draw_board(term: &Window, world: &World) {
@@ -79,20 +133,28 @@ fn draw_board(term: &Window, world: &World) {
 
     draw_border(term, world);
 
-    let level = world.player.current_level(&world.dungeon);
     for row in 0..grid::HEIGHT as i32 {
         term.mv(row + 4, 30);
         for col in 0..grid::WIDTH as i32 {
-            let position = Point(col, row);
-            let sprite =
-                if level.known_tiles.contains(&position) {
-                    let visible = world.player.visible.contains(&position);
-                    level.sprite_at(position, &world).darken(!visible)
-                } else {
-                    sprite::HIDDEN
-                };
-            term.addch(cell(sprite));
+            term.addch(board_cell(world, Point(col, row)));
+        }
+    }
+}
+
+fn board_cell(world: &World, position: Point) -> pancurses::chtype {
+    let level = world.player.current_level(&world.dungeon);
+    if level.known_tiles.contains(&position) {
+        let sprite = level.sprite_at(position, &world);
+        if world.player.visible.contains(&position) {
+            cell(sprite)
+        } else if world.player.scrying > 0 {
+            // out of sight but shown by the palantir
+            cell(sprite) | pancurses::chtype::from(Attribute::Underline)
+        } else {
+            cell(sprite.darken(true))
         }
+    } else {
+        cell(sprite::HIDDEN)
     }
 }
 
@@ -112,24 +174,87 @@ fn draw_border(term: &Window, world: &World) {
     }
 }
 
-fn draw_messages(term: &Window, log: &Log) {
+fn draw_messages(term: &Window, world: &World) {
     // TODO: depend on terminal size; don't hardcode lengths/alignments;
     //       visually group messages by turn (underscore separators? color?);
     //       maybe group similar messages; add linewrapping if needed
     term.attrset(Attributes::new());
-    for (i, &(_, ref message)) in log.recent_messages().iter().take(6).enumerate() {
-        term.mvaddstr(23 - i as i32, 0, &message);
+    for (i, &(_, ref message)) in world.log.recent_messages().iter().take(6).enumerate() {
+        term.mvaddstr(23 - i as i32, 0, &glitch(world, message, i as u32));
+    }
+}
+
+// Corrupt text in proportion to the text sync byte: characters turn into
+// random CP437 glyphs, lines shift over, and words stutter. `line` seeds the
+// corruption together with the turn count, so the screen holds still until
+// the next turn.
+fn glitch(world: &World, text: &str, line: u32) -> String {
+    let sync = world.player.text_sync as u32;
+    if sync == 0 {
+        return String::from(text)
+    }
+
+    // (XorShiftRng can't take an all-zero seed, hence the constant)
+    let mut rng = XorShiftRng::from_seed(
+        [world.log.turn() as u32, line, sync, 0x9e3779b9]);
+    let mut result = String::new();
+
+    if rng.gen_range(0, 256) < sync {
+        for _ in 0..rng.gen_range(1, 4) {
+            result.push(' ');
+        }
+    }
+
+    for (i, word) in text.split(' ').enumerate() {
+        let word: String = word.chars().map(|c|
+            if rng.gen_range(0, 512) < sync {
+                memory::CP437[rng.gen_range(0, 256)]
+            } else {
+                c
+            }).collect();
+
+        if i > 0 {
+            result.push(' ');
+        }
+        result.push_str(&word);
+
+        if rng.gen_range(0, 1024) < sync {
+            result.push(' ');
+            result.push_str(&word);
+        }
     }
+
+    result
 }
 
 fn draw_status(term: &Window, world: &World) {
-    draw_inventory(term, &world.player.inventory);
+    draw_stats(term, world);
+    draw_inventory(term, world);
 }
 
-fn draw_inventory(term: &Window, inventory: &Inventory) {
+fn draw_stats(term: &Window, world: &World) {
+    // TODO: depend on terminal size; don't hardcode lengths/alignments
+    let player = &world.player;
+    term.attrset(color(Color::Gray));
+    let stats = format!("HP {}  TP {}  XL {}  Def {}  Res {:+}/{:+}/{:+}/{:+}",
+        player.hp, player.tp, player.xl, player.def,
+        player.aptitude[0], player.aptitude[1], player.aptitude[2], player.aptitude[3]);
+    term.mvaddstr(1, 0, &glitch(world, &stats, 0x180));
+
+    let mut timers = String::new();
+    for &timer in timer::TIMERS.iter() {
+        if player.timer_active(timer) {
+            timers.push_str(&format!("{} {}  ", timer.name(), player.timer[timer as usize]));
+        }
+    }
+    term.attrset(color(Color::Yellow));
+    term.mvaddstr(2, 0, &glitch(world, &timers, 0x181));
+}
+
+fn draw_inventory(term: &Window, world: &World) {
     // TODO: depend on terminal size; don't hardcode lengths/alignments
     // TODO: abbreviate long inventory slot descriptions
-    for (index, slot) in inventory.slots.iter().enumerate() {
+    for (index, slot) in world.player.inventory.slots.iter().enumerate() {
         let index_color = if !slot.is_empty() {
             Color::Blue
         } else {
@@ -147,24 +272,9 @@ fn draw_inventory(term: &Window, inventory: &Inventory) {
         };
         term.attrset(color(text_color));
 
-        if slot.is_cursed() {
-            term.addstr(" cursed");
-        }
-
-        if slot.is_enchanted() {
-            term.addstr(" enchanted");
-        }
-
-        term.addch(' ');
-        let name = match slot.get_item() {
-            Some(item) => item.name(),
-            None => "nothing"
-        };
-        term.addstr(name);
-
-        if slot.is_equipped() {
-            term.addstr("(equipped)");
-        }
+        let description = slot.description(
+            world.player.identification, &world.item_appearance_map);
+        term.addstr(&glitch(world, &format!(" {}", description), 0x100 + index as u32));
     }
 }
 
@@ -175,8 +285,73 @@ fn draw_ram(term: &Window, world: &World) {
         term.mvaddstr(i as i32 / 8 + 4, i as i32 % 8 * 3 + 3, &*hex);
     }
 
-    term.mvaddstr(13, 3, util::address_name(world.player.selected));
+    term.mvaddstr(13, 3, &glitch(world, util::address_name(world.player.selected), 0x200));
 
     let name = memory::player_name(world);
-    term.mvaddstr(14, 3, &*name);
+    term.mvaddstr(14, 3, &glitch(world, &name, 0x201));
+
+    term.attrset(color(Color::Gray));
+    term.mvaddstr(15, 3, &format!("accumulator {:02x}", world.player.accumulator));
+}
+
+pub fn draw_spells(term: &Window, world: &World) {
+    // TODO: depend on terminal size; don't hardcode lengths/alignments
+    term.erase();
+
+    term.attrset(color(Color::White));
+    term.mvaddstr(1, 3, "Spells known:");
+
+    for (index, spell) in spell::SPELLS.iter().enumerate() {
+        let row = index as i32 * 2 + 3;
+        if world.player.spell_memory[spell.bit() as usize] {
+            term.attrset(color(Color::White));
+            term.mvaddstr(row, 3, &format!("{} {} {} TP", index + 1, spell.name(), spell.cost()));
+            term.attrset(color(Color::Gray));
+            for (i, line) in util::word_wrap(spell.description(), 60).iter().take(2).enumerate() {
+                term.mvaddstr(row + i as i32, 16, line);
+            }
+        } else {
+            term.attrset(color(Color::Dark));
+            term.mvaddstr(row, 3, &format!("{} ???", index + 1));
+        }
+    }
+
+    term.attrset(color(Color::Dark));
+    term.mvaddstr(20, 3, "Press any key to continue.");
+
+    term.refresh();
+}
+
+pub fn draw_game_over(term: &Window, world: &World, ending: &Ending, morgue_note: &str) {
+    // TODO: depend on terminal size; don't hardcode lengths/alignments
+    term.erase();
+
+    term.attrset(color(match *ending {
+        Ending::Death(_) => Color::Red,
+        Ending::Escape => Color::Yellow,
+    }));
+    term.mvaddstr(1, 3, &format!("{} {}. Score: {}",
+        memory::player_name(world), morgue::headline(world, ending), world.player.score()));
+
+    term.attrset(color(Color::Gray));
+    for i in 0x00..0x40 {
+        let hex = format!("{:02x}", memory::peek(world, i));
+        term.mvaddstr(i as i32 / 8 + 3, i as i32 % 8 * 3 + 3, &hex);
+    }
+
+    for (index, slot) in world.player.inventory.slots.iter().enumerate() {
+        let description = slot.description(
+            world.player.identification, &world.item_appearance_map);
+        term.mvaddstr(index as i32 + 3, 30, &format!("{} {}", index + 1, description));
+    }
+
+    for (i, &(_, ref message)) in world.log.recent_messages().iter().take(8).enumerate() {
+        term.mvaddstr(19 - i as i32, 3, message);
+    }
+
+    term.attrset(color(Color::Dark));
+    term.mvaddstr(21, 3, morgue_note);
+    term.mvaddstr(22, 3, "Press any key to exit.");
+
+    term.refresh();
 }
@@ -1,6 +1,13 @@
+use std::cmp::min;
 use dungeon::{self, Dungeon, Level};
+use geometry::Point;
+use fov::{self, FOV_RADIUS};
+use grid;
 use item;
+use memory;
 use player::Player;
+use tile::Tile;
+use util::{pick, random_range};
 use std;
 use std::io::Write;
 use log::Log;
@@ -15,6 +22,10 @@ pub struct World {
     pub player_appearance_byte: u8,
     pub door_appearance_byte: u8,
     pub wall_appearance_byte: u8,
+    pub floor_appearance_byte: u8,
+
+    pub light_radius_byte: u8,
+    pub instability_byte: u8,
 
     pub log: Log
 }
@@ -27,18 +38,25 @@ impl World {
         });
 
         let mut log = Log::new();
+        let item_appearance_map = item::random_appearance_map();
 
-        World {
-            player: Player::new(&mut log, &mut dungeon),
+        let mut world = World {
+            player: Player::new(&mut log, &mut dungeon, &item_appearance_map),
             dungeon: dungeon,
-            item_appearance_map: item::random_appearance_map(),
+            item_appearance_map: item_appearance_map,
 
             player_appearance_byte: 0b11111111, // white @
             door_appearance_byte: 0b11001010, // brown +
             wall_appearance_byte: 0b01100010, // teal #
+            floor_appearance_byte: 0b11101101, // gray .
+
+            light_radius_byte: FOV_RADIUS as u8,
+            instability_byte: 0,
 
             log: log
-        }
+        };
+        world.update_visibility();
+        world
     }
 
     pub fn current_level(&self) -> &Level {
@@ -48,4 +66,114 @@ impl World {
     pub fn current_level_mut(&mut self) -> &mut Level {
         self.player.current_level_mut(&mut self.dungeon)
     }
+
+    // make sure the player and monsters are somewhere real after anything
+    // that might have moved them: stairs, pokes, or random bit flips.
+    // after this, every position on the current level can index a Grid
+    pub fn settle(&mut self) {
+        loop {
+            if self.player.depth == 0 {
+                self.fall_through_level_zero();
+            } else if !grid::RECTANGLE.contains(self.player.position) {
+                self.zip_player();
+            } else if let Some(index) = self.current_level().monsters.iter()
+                .position(|m| m.alive() && !grid::RECTANGLE.contains(m.position))
+            {
+                self.zip_monster(index);
+            } else {
+                break
+            }
+        }
+
+        // a poke or bit flip may have changed what's equipped
+        self.player.update_equipment_bonus();
+    }
+
+    // "dungeon level 0" is a corrupted world: flip 50 random bits across RAM,
+    // then drop the player somewhere random on a random level
+    fn fall_through_level_zero(&mut self) {
+        self.log.tell("You fall through a crack in the world!");
+
+        // pick the destination first, so that bit flips hitting monster data
+        // land on the level the player is about to arrive on
+        self.player.depth = random_range(1..256) as u8;
+        memory::flip_random_bits(self, 50);
+
+        if self.player.depth == 0 {
+            // one of the flips sent us right back
+            return
+        }
+
+        let position = {
+            let level = self.current_level();
+            pick(grid::RECTANGLE.into_iter().filter(|&p|
+                level.tiles[p] == Tile::Floor && level.monster_at(p).is_none()))
+        };
+        self.player.position = position;
+
+        self.log.tell(format!("Reality reassembles itself around you on level {}.",
+            self.player.depth));
+    }
+
+    // positions 247-255 lie past the end of the board. the player zips
+    // through these unused cells back to 0, flipping one random bit per cell,
+    // then comes to rest on the first open tile
+    fn zip_player(&mut self) {
+        self.log.tell("You zip through the edge of the world! Bits flip around you.");
+
+        let cells = 256 - self.player.position.as_byte() as usize;
+        memory::flip_random_bits(self, cells);
+
+        if self.player.depth != 0 {
+            let landing = self.first_vacant_tile();
+            self.player.position = landing;
+        }
+    }
+
+    // same as zip_player, but for the monster in the given slot
+    fn zip_monster(&mut self, index: usize) {
+        let (name, cells) = {
+            let monster = &self.current_level().monsters[index];
+            (monster.name(), 256 - monster.position.as_byte() as usize)
+        };
+        self.log.tell(format!("The {} zips through the edge of the world!", name));
+
+        let depth = self.player.depth;
+        memory::flip_random_bits(self, cells);
+
+        // the flips may have moved the player to another level, or killed or
+        // already moved the monster
+        if self.player.depth == depth {
+            let monster = self.current_level().monsters[index];
+            if monster.alive() && !grid::RECTANGLE.contains(monster.position) {
+                let landing = self.first_vacant_tile();
+                self.current_level_mut().monsters[index].position = landing;
+            }
+        }
+    }
+
+    // the first open tile from position 0 not taken by the player or a monster
+    fn first_vacant_tile(&self) -> Point {
+        let level = self.current_level();
+        grid::RECTANGLE.into_iter().find(|&p|
+            level.tiles[p].is_open()
+                && level.monster_at(p).is_none()
+                && p != self.player.position)
+            .expect("World::first_vacant_tile - no room on level")
+    }
+
+    // recalculate what the player can see, e.g. after moving, opening a door,
+    // or a poke to the player's position or the light radius
+    pub fn update_visibility(&mut self) {
+        // no point in looking further than across the whole board
+        let radius = min(self.light_radius_byte as i32, grid::WIDTH as i32);
+        let visible = fov::calculate(self.current_level(), self.player.position, radius);
+        self.current_level_mut().known_tiles.extend(&visible);
+        self.player.visible = visible;
+
+        // the palantir shows every tile, even on levels entered while scrying
+        if self.player.scrying > 0 {
+            self.current_level_mut().known_tiles.extend(grid::RECTANGLE);
+        }
+    }
 }